@@ -0,0 +1,94 @@
+use hive_rust::logic::alphabeta::AlphaBeta;
+use hive_rust::logic::eval::EvalKind;
+use hive_rust::logic::game::Game;
+use hive_rust::logic::search::SearchSettings;
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: bench [DEPTH] [--evaluation PieceCount|QueenPressure] [--hash MB]";
+
+// Middlegame positions with both queens out and a few bugs already moved
+const POSITIONS: [&str; 4] = [
+    "Base;InProgress;White[10];wS1;bB1 \\wS1;wQ /wS1;bB2 bB1/;wB1 /wQ;bQ \\bB2;wA1 /wB1;\
+     bA1 bQ-;wS2 /wA1;bA1 -wS2;wA2 wQ-;bA1 bA1\\;wA2 \\bQ;bA1 wQ-;wA2 wS2-;bA2 bQ-;\
+     wA2 wA1-;bG1 -bB1",
+    "Base+M;InProgress;White[10];wG1;bA1 wG1/;wS1 -wG1;bQ bA1/;wB1 \\wS1;bS1 bQ-;\
+     wQ wS1\\;bS2 bA1-;wS2 -wB1;bG1 bS2\\;wB2 wS2/;bG1 \\bQ;wM wQ-;bA2 -bQ;wS2 wB2/;\
+     bA2 wQ\\;wS2 -bQ;bA2 -wQ",
+    "Base+L;InProgress;White[10];wA1;bS1 \\wA1;wA2 wA1-;bG1 bS1/;wG1 wA2\\;bG2 -bS1;\
+     wQ wG1\\;bQ \\bG1;wA3 wA2-;bQ -bG1;wL wA3/;bL -bQ;wS1 wL/;bG3 \\bL;wB1 wQ-;\
+     bS2 -bL;wS1 bS1-;bS2 bG3/",
+    "Base+P;InProgress;White[10];wS1;bG1 /wS1;wQ \\wS1;bB1 /bG1;wS2 wQ/;bS1 -bG1;\
+     wP wS2-;bQ -bS1;wP wQ-;bS2 -bB1;wG1 wS2-;bP bQ/;wA1 wS2/;bA1 bB1-;wB1 wP-;\
+     bA2 bB1\\;wG2 wB1\\;bA3 -bQ",
+];
+
+fn parse_args() -> Result<SearchSettings, String> {
+    let mut settings = SearchSettings {
+        max_depth: 5,
+        ..SearchSettings::default()
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--evaluation" => {
+                settings.evaluation = match iter.next().as_deref() {
+                    Some("PieceCount") => EvalKind::PieceCount,
+                    Some("QueenPressure") => EvalKind::QueenPressure,
+                    _ => return Err("invalid evaluation".to_string()),
+                }
+            }
+            "--hash" => {
+                let hash = iter.next().ok_or("missing hash size")?;
+                settings.hash_size_mb = hash
+                    .parse()
+                    .ok()
+                    .filter(|&h| h > 0)
+                    .ok_or(format!("invalid hash size {hash}"))?;
+            }
+            depth => {
+                settings.max_depth = depth
+                    .parse()
+                    .ok()
+                    .filter(|&d| d > 0)
+                    .ok_or(format!("invalid depth {depth}"))?
+            }
+        }
+    }
+    Ok(settings)
+}
+
+// Fixed depth searches of a few middlegame positions, each with an empty table
+fn main() {
+    let settings = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+
+    let mut total_nodes = 0;
+    let mut total_time = Duration::ZERO;
+    for (i, game_string) in POSITIONS.iter().enumerate() {
+        let mut game = Game::from_game_string(game_string).expect("Couldn't load position.");
+        let mut search = AlphaBeta::new(&settings);
+        let now = Instant::now();
+        let analysis = search.analyse(&mut game);
+        let elapsed = now.elapsed();
+        let best_move = analysis
+            .best_move
+            .map(|m| m.to_string())
+            .unwrap_or_default();
+        println!(
+            "position {}: depth {} score {} best {best_move}, {} nodes in {elapsed:?}",
+            i + 1,
+            analysis.depth,
+            analysis.score,
+            search.nodes()
+        );
+        total_nodes += search.nodes();
+        total_time += elapsed;
+    }
+    let kn_per_s = (total_nodes as f32) / (1000.0 * total_time.as_secs_f32());
+    println!("total: {total_nodes} nodes in {total_time:?}.");
+    println!("{kn_per_s} KN/s");
+}
@@ -1,132 +1,415 @@
-use crate::logic::game::{Game, GameState};
+use crate::error::HiveError;
+use crate::logic::alphabeta::AlphaBeta;
+use crate::logic::eval::EvalKind;
+use crate::logic::game::{DrawRules, Game, GameState};
 use crate::logic::r#move::Move;
+use crate::logic::search::{SearchAlgorithm, SearchSettings};
+use crate::options::Options;
 use log::{error, info, LevelFilter};
 use std::str::FromStr;
+use std::time::Duration;
 
 pub struct Engine {
     version: &'static str,
     pub game: Option<Game>,
+    options: Options,
+    // Kept between searches of a game so that its table is reused
+    search: Option<AlphaBeta>,
 }
 
 const VERSION: &str = "0.1";
 
+// Parse a HH:MM:SS time limit
+fn parse_time(time: &str) -> Result<Duration, HiveError> {
+    let invalid = || HiveError::Parse(format!("invalid time {time}, expected HH:MM:SS"));
+    let fields = time
+        .split(':')
+        .map(|field| field.parse::<u64>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u64>, HiveError>>()?;
+    match fields.as_slice() {
+        [hours, minutes, seconds] if (*minutes < 60) & (*seconds < 60) => {
+            let secs = hours * 3600 + minutes * 60 + seconds;
+            if secs == 0 {
+                return Err(invalid());
+            }
+            Ok(Duration::from_secs(secs))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Engine {
     pub fn new() -> Self {
-        simple_logging::log_to_file("test.log", LevelFilter::Error)
-            .expect("Couldn't initialize logger");
+        // Only the first engine of the process gets to set the logger
+        let _ = simple_logging::log_to_file("test.log", LevelFilter::Error);
         Engine {
             version: VERSION,
             game: None,
+            options: Options::new(),
+            search: None,
         }
     }
 
     fn info(&self) -> String {
-        format!("{}", self.version)
+        self.version.to_string()
     }
 
-    pub fn new_game(&mut self) -> String {
+    pub fn new_game(&mut self, game_string: Option<String>) -> Result<String, HiveError> {
         info!("starting new game");
-        let mut game = Game::new();
-        game.set_state(GameState::InProgress);
+        let game = match game_string {
+            Some(game_string) => {
+                Game::load(game_string.trim(), self.draw_rules()).map_err(|e| {
+                    error!("couldn't load game string: {e}");
+                    e
+                })?
+            }
+            None => {
+                let mut game = Game::new();
+                game.set_state(GameState::InProgress);
+                game.set_draw_rules(self.draw_rules());
+                game
+            }
+        };
+        info!("turn number: {}", game.turn_number);
+        info!("turn color: {:?}", game.turn_color);
+        let game_string = game.full_game_string();
         self.game = Some(game);
-        info!("turn number: {}", self.game.as_ref().unwrap().turn_number);
-        info!("turn color: {:?}", self.game.as_ref().unwrap().turn_color);
-        self.game.as_ref().expect("No game found.").game_string()
+        if let Some(search) = self.search.as_mut() {
+            search.clear();
+        }
+        Ok(game_string)
     }
 
-    pub fn play(&mut self, move_str: String) -> Result<String, String> {
+    pub fn play(&mut self, move_str: String) -> Result<String, HiveError> {
         info!("new move requested: {move_str}");
-        let m = Move::from_str(move_str.as_str()).expect("Couldn't read move");
+        let game = self.game.as_mut().ok_or(HiveError::NoGameInProgress)?;
+        let m = Move::from_str(move_str.as_str())
+            .map_err(|_| HiveError::InvalidMove(move_str.clone()))?;
         info!("{m}");
-        let valid_moves = self.game.as_mut().unwrap().compute_valid_moves();
-        if valid_moves.contains(&m) {
-            self.game.as_mut().unwrap().play_move(m);
-            self.game.as_mut().unwrap().update_game_state();
-
-            let game_string = self
-                .game
-                .as_ref()
-                .expect("Couldn't find game.")
-                .game_string();
-            let moves_string = self
-                .game
-                .as_ref()
-                .expect("Couldn't find game.")
-                .moves_string();
-            info!("move {move_str} played");
-            info!("turn number: {}", self.game.as_ref().unwrap().turn_number);
-            info!("turn color: {:?}", self.game.as_ref().unwrap().turn_color);
-            Ok(format!("{game_string};{moves_string}"))
-        } else {
-            error!("{}", format!("invalid move {move_str}"));
-            Err(format!("invalid move {move_str}"))
-        }
-    }
-
-    pub fn pass(&self) -> String {
-        "pass not implemented yet.".to_string()
-    }
-
-    pub fn valid_moves(&mut self) -> String {
+        match game.try_play_move(m) {
+            Ok(()) => {
+                info!("move {move_str} played");
+                info!("turn number: {}", game.turn_number);
+                info!("turn color: {:?}", game.turn_color);
+                Ok(game.full_game_string())
+            }
+            Err(e) => {
+                error!("{e}");
+                Err(e)
+            }
+        }
+    }
+
+    pub fn undo(&mut self, n_moves_str: Option<String>) -> Result<String, HiveError> {
+        let n_moves = match n_moves_str {
+            Some(n) => n
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| HiveError::Parse(format!("invalid number of moves to undo {n}")))?,
+            None => 1,
+        };
+        let game = self.game.as_mut().ok_or(HiveError::NoGameInProgress)?;
+        if n_moves > game.n_moves() {
+            error!("can't undo {n_moves} moves");
+            return Err(HiveError::InvalidCommand(format!(
+                "can't undo {n_moves} moves, only {} played",
+                game.n_moves()
+            )));
+        }
+        for _ in 0..n_moves {
+            if let Some(m) = game.undo_move() {
+                info!("move {m} undone");
+            }
+        }
+        Ok(game.full_game_string())
+    }
+
+    pub fn pass(&mut self) -> Result<String, HiveError> {
+        self.play("pass".to_string())
+    }
+
+    pub fn valid_moves(&mut self) -> Result<String, HiveError> {
         info!("requesting valid moves");
+        self.game_in_progress()?;
+        let game = self.game.as_mut().ok_or(HiveError::NoGameInProgress)?;
         let mut moves_str = vec![];
-        for m in self.game.as_mut().unwrap().compute_valid_moves() {
+        for m in game.compute_valid_moves() {
             moves_str.push(format!("{m}"))
         }
-        moves_str.join(";")
+        Ok(moves_str.join(";"))
     }
 
-    pub fn best_move(&self) -> String {
-        let best_move = self.game.as_ref().unwrap().get_best_move();
-        if let Some(m) = best_move {
-            format!("{m}")
-        } else {
-            "".to_string()
+    pub fn best_move(&mut self, limit: Option<String>) -> Result<String, HiveError> {
+        self.game_in_progress()?;
+        let mut settings = self.search_settings();
+        let limit = limit.unwrap_or_default();
+        match limit.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [] => {}
+            ["time", time] => {
+                settings.max_time = Some(parse_time(time)?);
+                // Only iterative searches can stop on time
+                if settings.algorithm == SearchAlgorithm::Negamax {
+                    settings.algorithm = SearchAlgorithm::Iterative;
+                }
+            }
+            ["depth", depth] => {
+                settings.max_depth = depth
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|&d| d > 0)
+                    .ok_or(HiveError::Parse(format!("invalid depth {depth}")))?;
+                settings.max_time = None;
+            }
+            _ => {
+                return Err(HiveError::InvalidCommand(format!(
+                    "invalid bestmove limit {limit}"
+                )))
+            }
         }
+        info!("searching best move with {settings:?}");
+
+        let mut game = self.game_in_progress()?.clone();
+        let best_move = match settings.algorithm {
+            SearchAlgorithm::AlphaBeta => {
+                let search = self.search.get_or_insert_with(|| AlphaBeta::new(&settings));
+                search.set_settings(&settings);
+                search.search(&mut game)
+            }
+            _ => game.get_best_move(&settings),
+        };
+        // A search stopped before completing its first depth has no move
+        let best_move = best_move.or_else(|| game.compute_valid_moves().first().cloned());
+        Ok(best_move.map(|m| m.to_string()).unwrap_or_default())
     }
 
-    pub fn options(&self) -> String {
-        "DummyOption;bool;False;False".to_string()
+    // Game that still has moves to play
+    fn game_in_progress(&self) -> Result<&Game, HiveError> {
+        let game = self.game.as_ref().ok_or(HiveError::NoGameInProgress)?;
+        match game.state {
+            GameState::WhiteWins | GameState::BlackWins | GameState::Draw => {
+                Err(HiveError::GameOver)
+            }
+            _ => Ok(game),
+        }
     }
 
-    pub fn process_command(&mut self, command: String) -> Result<String, String> {
-        let command_stripped = command.strip_suffix('\n').unwrap().to_string();
-        let keyword: String;
-        let args: String;
-        info!("new command received: {command_stripped}");
-        if command.contains(' ') {
-            let split_index = command_stripped.find(' ').unwrap();
-            (keyword, args) = (
-                command.get(0..split_index).unwrap().to_string(),
-                command
-                    .get(split_index + 1..)
-                    .unwrap()
-                    .strip_suffix("\n")
-                    .unwrap()
-                    .to_string(),
-            );
-            match keyword.as_str() {
-                "play" => self.play(args),
-                "newgame" => Ok(self.new_game()),
-                _ => {
-                    error!("Unknown command!");
-                    Err("Unknown command.".to_string())
+    fn draw_rules(&self) -> DrawRules {
+        DrawRules {
+            repetition: self.options.get_bool("RepetitionDraw"),
+            max_turns: self.options.get_int("MaxTurns") as u32,
+        }
+    }
+
+    fn search_settings(&self) -> SearchSettings {
+        let algorithm = match self.options.get_enum("SearchAlgorithm") {
+            "Negamax" => SearchAlgorithm::Negamax,
+            "Iterative" => SearchAlgorithm::Iterative,
+            "Parallel" => SearchAlgorithm::Parallel,
+            _ => SearchAlgorithm::AlphaBeta,
+        };
+        let evaluation = match self.options.get_enum("Evaluation") {
+            "QueenPressure" => EvalKind::QueenPressure,
+            _ => EvalKind::PieceCount,
+        };
+        let max_time = self.options.get_double("MaxTime");
+        SearchSettings {
+            algorithm,
+            evaluation,
+            max_depth: self.options.get_int("MaxDepth") as u8,
+            max_time: (max_time > 0.0).then(|| Duration::from_secs_f64(max_time)),
+            threads: self.options.get_int("MaxThreads") as usize,
+            hash_size_mb: self.options.get_int("HashSizeMB") as usize,
+            ..SearchSettings::default()
+        }
+    }
+
+    pub fn options(&mut self, args: Option<String>) -> Result<String, HiveError> {
+        let args = args.unwrap_or_default();
+        let args: Vec<&str> = args.split_whitespace().collect();
+        match args.as_slice() {
+            [] => Ok(self
+                .options
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")),
+            ["get", name] => Ok(self.options.get(name)?.to_string()),
+            ["set", name, value] => {
+                let option_string = self.options.set(name, value)?.to_string();
+                info!("option set: {option_string}");
+                let draw_rules = self.draw_rules();
+                if let Some(game) = self.game.as_mut() {
+                    game.set_draw_rules(draw_rules);
+                }
+                Ok(option_string)
+            }
+            _ => Err(HiveError::InvalidCommand(
+                "invalid options command".to_string(),
+            )),
+        }
+    }
+
+    // Full reply to a command as sent to GUIs, the response lines followed by ok
+    pub fn uhp_response(&mut self, command: String) -> String {
+        match self.process_command(command) {
+            Ok(r) if r.is_empty() => "ok\n".to_string(),
+            Ok(r) => format!("{r}\nok\n"),
+            Err(e) => format!("{}\nok\n", e.uhp_line()),
+        }
+    }
+
+    pub fn process_command(&mut self, command: String) -> Result<String, HiveError> {
+        let command = command.trim_end_matches(['\r', '\n']);
+        info!("new command received: {command}");
+        match command.split_once(' ') {
+            Some((keyword, args)) => {
+                let args = args.to_string();
+                match keyword {
+                    "play" => self.play(args),
+                    "newgame" => self.new_game(Some(args)),
+                    "undo" => self.undo(Some(args)),
+                    "options" => self.options(Some(args)),
+                    "bestmove" => self.best_move(Some(args)),
+                    _ => {
+                        error!("Unknown command!");
+                        Err(HiveError::InvalidCommand(format!(
+                            "unknown command {keyword}"
+                        )))
+                    }
                 }
             }
-        } else {
-            keyword = command_stripped;
-            match keyword.as_str() {
+            None => match command {
                 "info" => Ok(self.info()),
-                "newgame" => Ok(self.new_game()),
-                "pass" => Err(self.pass()),
-                "validmoves" => Ok(self.valid_moves()),
-                "bestmove" => Ok(self.best_move()),
-                "options" => Ok(self.options()),
+                "newgame" => self.new_game(None),
+                "pass" => self.pass(),
+                "undo" => self.undo(None),
+                "validmoves" => self.valid_moves(),
+                "bestmove" => self.best_move(None),
+                "options" => self.options(None),
                 _ => {
                     error!("Unknown command!");
-                    Err("Unknown command.".to_string())
+                    Err(HiveError::InvalidCommand(format!(
+                        "unknown command {command}"
+                    )))
                 }
-            }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{parse_time, Engine};
+    use crate::error::HiveError;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn parse_time_limits() {
+        assert_eq!(parse_time("00:00:05"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_time("01:02:03"), Ok(Duration::from_secs(3723)));
+        for invalid in ["00:00:00", "5", "00:60:00", "00:00:xx", "00:00:00:01"] {
+            assert!(parse_time(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn malformed_commands_are_errors() {
+        let mut engine = Engine::new();
+        for command in ["", "\n", "play wQ\n", "validmoves", "bestmove", "undo 1"] {
+            assert_eq!(
+                engine.process_command(command.to_string()).err(),
+                Some(if command.is_empty() | (command == "\n") {
+                    HiveError::InvalidCommand("unknown command ".to_string())
+                } else {
+                    HiveError::NoGameInProgress
+                }),
+                "{command:?}"
+            );
+        }
+
+        engine.process_command("newgame Base".to_string()).unwrap();
+        for move_str in ["", "wX1", "wQ -", "wQ bQ/ x", "wQ wQ-", "wS1 -wX"] {
+            let err = engine
+                .process_command(format!("play {move_str}"))
+                .unwrap_err();
+            assert_eq!(err, HiveError::InvalidMove(move_str.to_string()));
+        }
+        assert!(engine
+            .process_command("newgame Base+X".to_string())
+            .is_err());
+        assert!(engine.process_command("undo many".to_string()).is_err());
+        assert!(engine
+            .process_command("bestmove forever".to_string())
+            .is_err());
+        assert!(engine.process_command("dance".to_string()).is_err());
+    }
+
+    #[test]
+    fn best_move_limits() {
+        let mut engine = Engine::new();
+        engine.process_command("newgame Base".to_string()).unwrap();
+        let valid_moves = engine.process_command("validmoves".to_string()).unwrap();
+        let valid_moves: Vec<&str> = valid_moves.split(';').collect();
+
+        let best_move = engine
+            .process_command("bestmove depth 2".to_string())
+            .unwrap();
+        assert!(valid_moves.contains(&best_move.as_str()), "{best_move}");
+
+        let start = Instant::now();
+        let best_move = engine
+            .process_command("bestmove time 00:00:01".to_string())
+            .unwrap();
+        assert!(valid_moves.contains(&best_move.as_str()), "{best_move}");
+        assert!(start.elapsed() < Duration::from_secs(3));
+
+        // The limit only applies to the search it comes with
+        assert!(engine
+            .process_command("bestmove depth 0".to_string())
+            .is_err());
+        assert!(engine
+            .process_command("bestmove time 00:00:00".to_string())
+            .is_err());
+        engine.process_command(format!("play {best_move}")).unwrap();
+        assert!(engine
+            .process_command("bestmove depth 1".to_string())
+            .is_ok());
+    }
+
+    #[test]
+    fn errors_as_uhp_lines() {
+        let mut engine = Engine::new();
+        engine
+            .process_command("newgame Base\r\n".to_string())
+            .unwrap();
+        engine.process_command("play wS1".to_string()).unwrap();
+        let err = engine
+            .process_command("play bQ wS2-".to_string())
+            .unwrap_err();
+        assert_eq!(err.uhp_line(), "invalidmove invalid move bQ wS2-");
+        let err = engine
+            .process_command("options get Dummy".to_string())
+            .unwrap_err();
+        assert_eq!(err.uhp_line(), "err unknown option Dummy");
+
+        // Black surrounded the white queen with its last move
+        engine
+            .process_command(
+                "newgame Base;BlackWins;White[9];wS1;bA1 /wS1;wS2 \\wS1;bQ -bA1;wQ wS2/;\
+                 bS1 bQ\\;wA1 wS2-;bG1 -bQ;wA2 \\wQ;bB1 -bG1;wB1 -wQ;bG2 bS1\\;wA2 wQ-;\
+                 bA2 bB1\\;wA3 wQ/;bA2 -wA3"
+                    .to_string(),
+            )
+            .unwrap();
+        for command in ["pass", "validmoves", "bestmove", "bestmove depth 1"] {
+            let err = engine.process_command(command.to_string()).unwrap_err();
+            assert_eq!(err.uhp_line(), "err game is over", "{command}");
         }
     }
 }
@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HiveError {
+    // Input that can't be read, with a description of what was wrong
+    Parse(String),
+    // Move string that is not legal in the current position
+    InvalidMove(String),
+    NoGameInProgress,
+    GameOver,
+    UnsupportedGameType(String),
+    // Command or argument the engine doesn't handle
+    InvalidCommand(String),
+}
+
+impl HiveError {
+    // Line answering a UHP command that failed
+    pub fn uhp_line(&self) -> String {
+        match self {
+            HiveError::InvalidMove(_) => format!("invalidmove {self}"),
+            _ => format!("err {self}"),
+        }
+    }
+}
+
+impl Display for HiveError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            HiveError::Parse(description) => write!(f, "{description}"),
+            HiveError::InvalidMove(m) => write!(f, "invalid move {m}"),
+            HiveError::NoGameInProgress => write!(f, "no game in progress"),
+            HiveError::GameOver => write!(f, "game is over"),
+            HiveError::UnsupportedGameType(gtype) => write!(
+                f,
+                "invalid game type {gtype}, expected Base or Base+ followed by M, L and P in that order"
+            ),
+            HiveError::InvalidCommand(description) => write!(f, "{description}"),
+        }
+    }
+}
+
+impl Error for HiveError {}
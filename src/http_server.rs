@@ -0,0 +1,248 @@
+use hive_rust::error::HiveError;
+use hive_rust::logic::alphabeta::AlphaBeta;
+use hive_rust::logic::eval::EvalKind;
+use hive_rust::logic::game::{DrawRules, Game};
+use hive_rust::logic::r#move::Move;
+use hive_rust::logic::search::SearchSettings;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::env;
+use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USAGE: &str = "usage: server-http [--address HOST:PORT]";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8183";
+// Longest search a request can ask for, in seconds
+const MAX_TIME: f64 = 60.0;
+// Requests are searched in parallel, each with its own table
+const HASH_SIZE_MB: usize = 16;
+// Requests handled at the same time, the others wait for a free worker
+const WORKERS: usize = 4;
+
+fn parse_args() -> Result<String, String> {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--address" => address = iter.next().ok_or("missing address")?,
+            arg => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(address)
+}
+
+#[derive(Deserialize)]
+struct ValidMovesRequest {
+    game: String,
+}
+
+#[derive(Serialize)]
+struct ValidMovesResponse {
+    game: String,
+    moves: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PlayRequest {
+    game: String,
+    #[serde(rename = "move")]
+    m: String,
+}
+
+#[derive(Serialize)]
+struct PlayResponse {
+    game: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct BestMoveRequest {
+    game: String,
+    depth: Option<u8>,
+    // Seconds, takes precedence over depth, searches never last more than MAX_TIME
+    time: Option<f64>,
+    evaluation: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BestMoveResponse {
+    #[serde(rename = "move")]
+    m: Option<String>,
+    // Game string once the best move is played
+    game: String,
+    // Score for the player to move
+    evaluation: i32,
+    depth: u8,
+    pv: Vec<String>,
+}
+
+// Errors are sent back with the status code of the reply
+enum HttpError {
+    BadRequest(String),
+    NotFound,
+    MethodNotAllowed,
+}
+
+impl From<HiveError> for HttpError {
+    fn from(e: HiveError) -> Self {
+        HttpError::BadRequest(e.to_string())
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, HttpError> {
+    serde_json::from_str(body).map_err(|e| HttpError::BadRequest(format!("invalid request: {e}")))
+}
+
+fn load_game(game_string: &str) -> Result<Game, HttpError> {
+    Ok(Game::load(game_string.trim(), DrawRules::default())?)
+}
+
+fn valid_moves(request: ValidMovesRequest) -> Result<ValidMovesResponse, HttpError> {
+    let mut game = load_game(&request.game)?;
+    Ok(ValidMovesResponse {
+        game: game.full_game_string(),
+        moves: game
+            .compute_valid_moves()
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+    })
+}
+
+fn play(request: PlayRequest) -> Result<PlayResponse, HttpError> {
+    let mut game = load_game(&request.game)?;
+    let m = Move::from_str(&request.m).map_err(|_| HiveError::InvalidMove(request.m.clone()))?;
+    game.try_play_move(m)?;
+    Ok(PlayResponse {
+        game: game.full_game_string(),
+        state: format!("{:?}", game.state),
+    })
+}
+
+fn best_move(request: BestMoveRequest) -> Result<BestMoveResponse, HttpError> {
+    let mut game = load_game(&request.game)?;
+    let evaluation = match request.evaluation.as_deref() {
+        None | Some("PieceCount") => EvalKind::PieceCount,
+        Some("QueenPressure") => EvalKind::QueenPressure,
+        Some(evaluation) => {
+            return Err(HttpError::BadRequest(format!(
+                "invalid evaluation {evaluation}"
+            )))
+        }
+    };
+    let max_time = match request.time {
+        Some(time) if (time > 0.0) & (time <= MAX_TIME) => Some(Duration::from_secs_f64(time)),
+        Some(time) => return Err(HttpError::BadRequest(format!("invalid time {time}"))),
+        None => None,
+    };
+    let settings = SearchSettings {
+        evaluation,
+        max_depth: match request.depth {
+            Some(depth) if (1..=20).contains(&depth) => depth,
+            Some(depth) => return Err(HttpError::BadRequest(format!("invalid depth {depth}"))),
+            None => SearchSettings::default().max_depth,
+        },
+        max_time,
+        // Depth searches are stopped too, whatever depth they reached
+        time_cap: Some(Duration::from_secs_f64(MAX_TIME)),
+        hash_size_mb: HASH_SIZE_MB,
+        ..SearchSettings::default()
+    };
+
+    let analysis = AlphaBeta::new(&settings).analyse(&mut game);
+    if let Some(m) = analysis.best_move {
+        game.try_play_move(m)?;
+    }
+    Ok(BestMoveResponse {
+        m: analysis.best_move.map(|m| m.to_string()),
+        game: game.full_game_string(),
+        evaluation: analysis.score,
+        depth: analysis.depth,
+        pv: analysis
+            .principal_variation
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+    })
+}
+
+// Every request carries the whole game so nothing is kept between requests
+fn route(method: &Method, url: &str, body: &str) -> Result<String, HttpError> {
+    let endpoint = url.split('?').next().unwrap_or_default();
+    if !["/validmoves", "/play", "/bestmove"].contains(&endpoint) {
+        return Err(HttpError::NotFound);
+    }
+    if *method != Method::Post {
+        return Err(HttpError::MethodNotAllowed);
+    }
+    match endpoint {
+        "/validmoves" => Ok(json!(valid_moves(parse_body(body)?)?).to_string()),
+        "/play" => Ok(json!(play(parse_body(body)?)?).to_string()),
+        _ => Ok(json!(best_move(parse_body(body)?)?).to_string()),
+    }
+}
+
+fn handle_request(mut request: Request) {
+    let mut body = String::new();
+    let (status, json) = match request.as_reader().read_to_string(&mut body) {
+        Err(e) => (
+            400,
+            json!({ "error": format!("invalid body: {e}") }).to_string(),
+        ),
+        Ok(_) => match route(request.method(), request.url(), &body) {
+            Ok(json) => (200, json),
+            Err(HttpError::BadRequest(e)) => (400, json!({ "error": e }).to_string()),
+            Err(HttpError::NotFound) => (404, json!({ "error": "not found" }).to_string()),
+            Err(HttpError::MethodNotAllowed) => {
+                (405, json!({ "error": "method not allowed" }).to_string())
+            }
+        },
+    };
+    println!("{} {} {status}", request.method(), request.url());
+
+    let header =
+        Header::from_bytes("Content-Type", "application/json").expect("Couldn't create header.");
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to write response: {e}");
+    }
+}
+
+fn main() {
+    let address = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+    let server = Server::http(&address).unwrap_or_else(|e| {
+        eprintln!("failed to bind to {address}: {e}");
+        process::exit(1);
+    });
+    // Port 0 binds to any free port, the actual one is printed
+    match server.server_addr().to_ip() {
+        Some(address) => println!("Server listening on {address}"),
+        None => println!("Server listening on {address}"),
+    }
+
+    // Searches can be long, a few workers take requests as they come
+    let server = Arc::new(server);
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle_request(request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod engine;
+pub mod error;
+pub mod logic;
+pub mod options;
+pub mod stats;
@@ -0,0 +1,540 @@
+use crate::logic::bugs::bug::{Bug, BugKind, Color};
+use crate::logic::eval::Eval;
+use crate::logic::game::{Game, GameState};
+use crate::logic::hive::Hive;
+use crate::logic::r#move::Move;
+use crate::logic::search::SearchSettings;
+use crate::logic::tile::Tile;
+use log::info;
+use minimax::Evaluator;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::time::Instant;
+
+const INFINITY: i32 = 1_000_000;
+// Score of a won position, minus the number of plies needed to get there
+const WIN: i32 = 100_000;
+// Depth reached when searching on time only
+const MAX_DEPTH: u8 = 64;
+// Scores beyond this are wins found in the search tree
+const WIN_BOUND: i32 = WIN - MAX_DEPTH as i32;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bound {
+    Exact,
+    // Score is at least this value
+    Lower,
+    // Score is at most this value
+    Upper,
+}
+
+// Win scores count plies from the root, they are stored counting from the
+// position itself so that they stay right when it is reached by another path
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    match score {
+        s if s >= WIN_BOUND => s + ply as i32,
+        s if s <= -WIN_BOUND => s - ply as i32,
+        s => s,
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    match score {
+        s if s >= WIN_BOUND => s - ply as i32,
+        s if s <= -WIN_BOUND => s + ply as i32,
+        s => s,
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    key: u64,
+    depth: u8,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+    // Search which stored the entry
+    generation: u8,
+}
+
+// Fixed size table indexed by the position Zobrist key
+struct TranspositionTable {
+    size_mb: usize,
+    entries: Vec<Option<Entry>>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    fn new(size_mb: usize) -> Self {
+        let len = ((size_mb << 20) / size_of::<Option<Entry>>()).max(1);
+        TranspositionTable {
+            size_mb,
+            entries: vec![None; len],
+            generation: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    // Entries of earlier searches are still used but can be replaced by shallower ones
+    fn new_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    fn get(&self, key: u64) -> Option<Entry> {
+        self.entries[self.index(key)].filter(|entry| entry.key == key)
+    }
+
+    // Deeper results of the current search are kept over shallower ones of the same position
+    fn store(&mut self, entry: Entry) {
+        let index = self.index(entry.key);
+        match self.entries[index] {
+            Some(old)
+                if (old.key == entry.key)
+                    & (old.generation == self.generation)
+                    & (old.depth > entry.depth) => {}
+            _ => self.entries[index] = Some(entry),
+        }
+    }
+}
+
+// Result of a search, the score is given for the player to move
+#[derive(Clone)]
+pub struct Analysis {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    // Last depth fully searched
+    pub depth: u8,
+    pub principal_variation: Vec<Move>,
+}
+
+// Earliest of the search time and its cap, from now
+fn deadline(settings: &SearchSettings) -> Option<Instant> {
+    [settings.max_time, settings.time_cap]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|time| Instant::now() + time)
+}
+
+// Alpha-beta search with iterative deepening and a transposition table,
+// the table is kept from one search to the next until cleared
+pub struct AlphaBeta {
+    eval: Eval,
+    max_depth: u8,
+    deadline: Option<Instant>,
+    table: TranspositionTable,
+    // Two quiet moves per ply which caused a cutoff in a sibling node
+    killers: Vec<[Option<Move>; 2]>,
+    // How often moving a bug to a tile caused a cutoff, weighted by depth
+    history: HashMap<(Bug, Tile), i32>,
+    root_best: Option<Move>,
+    // Set when a score depends on the moves leading to the position, such
+    // scores are not stored as another path may not draw
+    path_dependent: bool,
+    // A search is never stopped before completing its first depth
+    can_stop: bool,
+    stopped: bool,
+    nodes: u64,
+}
+
+impl AlphaBeta {
+    pub fn new(settings: &SearchSettings) -> Self {
+        AlphaBeta {
+            eval: Eval {
+                kind: settings.evaluation,
+            },
+            max_depth: match settings.max_time {
+                Some(_) => MAX_DEPTH,
+                None => settings.max_depth,
+            },
+            deadline: deadline(settings),
+            table: TranspositionTable::new(settings.hash_size_mb),
+            killers: vec![],
+            history: HashMap::new(),
+            root_best: None,
+            path_dependent: false,
+            can_stop: false,
+            stopped: false,
+            nodes: 0,
+        }
+    }
+
+    // Get ready for the next search, what was learnt so far is kept unless
+    // the evaluation changed or the table has to be resized
+    pub fn set_settings(&mut self, settings: &SearchSettings) {
+        if settings.hash_size_mb != self.table.size_mb {
+            self.table = TranspositionTable::new(settings.hash_size_mb);
+        } else if settings.evaluation != self.eval.kind {
+            self.clear();
+        }
+        self.eval = Eval {
+            kind: settings.evaluation,
+        };
+        self.max_depth = match settings.max_time {
+            Some(_) => MAX_DEPTH,
+            None => settings.max_depth,
+        };
+        self.deadline = deadline(settings);
+        self.table.new_generation();
+        // Killers are tied to plies from the root, which moved
+        self.killers.clear();
+        self.history.values_mut().for_each(|count| *count /= 2);
+        self.root_best = None;
+        self.can_stop = false;
+        self.stopped = false;
+        self.nodes = 0;
+    }
+
+    // Positions visited since the search started
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    // Forget every position, for a new game
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.killers.clear();
+        self.history.clear();
+    }
+
+    // Best move found by the last completed depth
+    pub fn search(&mut self, game: &mut Game) -> Option<Move> {
+        let moves = game.compute_valid_moves();
+        if moves.len() == 1 {
+            return moves.first().cloned();
+        }
+        self.analyse(game).best_move
+    }
+
+    // Same as search, also keeping the score and the line leading to it
+    pub fn analyse(&mut self, game: &mut Game) -> Analysis {
+        let mut analysis = Analysis {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            principal_variation: vec![],
+        };
+        self.path_dependent = false;
+        for depth in 1..=self.max_depth {
+            let Some(score) = self.negamax(game, depth, 0, -INFINITY, INFINITY) else {
+                break;
+            };
+            analysis.best_move = self.root_best;
+            analysis.score = score;
+            analysis.depth = depth;
+            self.can_stop = true;
+            info!("depth {depth} score {score} nodes {}", self.nodes);
+            // No need to look further once the game outcome is known
+            if score.abs() >= WIN_BOUND {
+                break;
+            }
+        }
+        analysis.principal_variation =
+            self.principal_variation(game, analysis.best_move, analysis.depth);
+        analysis
+    }
+
+    // Best moves stored in the table from the root, starting with the best move
+    // found, the table may have lost some of them so the line can be shorter
+    fn principal_variation(
+        &self,
+        game: &mut Game,
+        best_move: Option<Move>,
+        depth: u8,
+    ) -> Vec<Move> {
+        let mut line = vec![];
+        let mut undos = vec![];
+        let mut next = best_move;
+        while let Some(m) = next {
+            if (line.len() >= depth as usize) | !game.compute_valid_moves().contains(&m) {
+                break;
+            }
+            line.push(m);
+            undos.push(game.make_move(m));
+            if (game.state != GameState::InProgress) | (game.position_occurrences() > 1) {
+                break;
+            }
+            next = self
+                .table
+                .get(game.position_hash())
+                .and_then(|e| e.best_move);
+        }
+        for undo in undos.into_iter().rev() {
+            game.unmake_move(undo);
+        }
+        line
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if self.can_stop
+            & self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    // Score of the position for the player to move, None if the search ran out of time
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        depth: u8,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> Option<i32> {
+        self.nodes += 1;
+        if self.out_of_time() {
+            return None;
+        }
+
+        let winner = match game.state {
+            GameState::WhiteWins => Some(Color::White),
+            GameState::BlackWins => Some(Color::Black),
+            // Drawn by repetition or by the number of turns played
+            GameState::Draw => {
+                self.path_dependent = true;
+                return Some(0);
+            }
+            _ => None,
+        };
+        if let Some(winner) = winner {
+            let score = WIN - ply as i32;
+            return Some(if winner == game.turn_color {
+                score
+            } else {
+                -score
+            });
+        }
+        // Going back to an earlier position leads nowhere
+        if (ply > 0) & (game.position_occurrences() > 1) {
+            self.path_dependent = true;
+            return Some(0);
+        }
+        if depth == 0 {
+            return Some(self.eval.evaluate(game) as i32);
+        }
+
+        let key = game.position_hash();
+        let entry = self.table.get(key);
+        if let Some(entry) = entry.filter(|e| (ply > 0) & (e.depth >= depth)) {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return Some(score),
+                Bound::Lower if score >= beta => return Some(score),
+                Bound::Upper if score <= alpha => return Some(score),
+                _ => {}
+            }
+        }
+
+        let mut moves = game.compute_valid_moves();
+        self.order_moves(game, &mut moves, entry.and_then(|e| e.best_move), ply);
+
+        let alpha_orig = alpha;
+        let parent_path_dependent = self.path_dependent;
+        self.path_dependent = false;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for m in moves {
+            let undo = game.make_move(m);
+            let score = self.negamax(game, depth - 1, ply + 1, -beta, -alpha);
+            game.unmake_move(undo);
+            let score = -score?;
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                self.record_cutoff(game, m, depth, ply);
+                break;
+            }
+        }
+
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        if !self.path_dependent {
+            self.table.store(Entry {
+                key,
+                depth,
+                score: score_to_table(best_score, ply),
+                bound,
+                best_move,
+                generation: self.table.generation,
+            });
+        }
+        self.path_dependent |= parent_path_dependent;
+        if ply == 0 {
+            self.root_best = best_move;
+        }
+        Some(best_score)
+    }
+
+    fn record_cutoff(&mut self, game: &Game, m: Move, depth: u8, ply: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+
+        let hive = game.get_hive().as_ref().expect("Couldn't get hive.");
+        if let (Some(bug), Some(tile)) = (m.source, hive.move_destination(&m)) {
+            *self.history.entry((bug, tile)).or_default() += depth as i32 * depth as i32;
+        }
+    }
+
+    // Transposition table move first, then moves attacking the opponent queen,
+    // killer moves and finally moves sorted by history
+    fn order_moves(&self, game: &Game, moves: &mut [Move], tt_move: Option<Move>, ply: usize) {
+        let hive = game.get_hive().as_ref().expect("Couldn't get hive.");
+        let opponent_queen = Bug::new(BugKind::Queen, 0, game.turn_color.opposite());
+        let queen_tile = hive.find_bug(&opponent_queen);
+        let killers = self.killers.get(ply).cloned().unwrap_or([None; 2]);
+
+        moves.sort_by_cached_key(|m| {
+            let score = if Some(*m) == tt_move {
+                4_000_000
+            } else if queen_tile.is_some_and(|tile| attacks_tile(hive, m, tile)) {
+                3_000_000
+            } else if Some(*m) == killers[0] {
+                2_000_001
+            } else if Some(*m) == killers[1] {
+                2_000_000
+            } else {
+                let destination = hive.move_destination(m);
+                match (m.source, destination) {
+                    (Some(bug), Some(tile)) => *self.history.get(&(bug, tile)).unwrap_or(&0),
+                    _ => 0,
+                }
+            };
+            Reverse(score)
+        });
+    }
+}
+
+// Whether a move brings a bug next to or on top of the tile
+fn attacks_tile(hive: &Hive, m: &Move, tile: Tile) -> bool {
+    let touches = |t: Tile| (t == tile) | tile.neighbors().contains(&t);
+    let Some(destination) = hive.move_destination(m) else {
+        return false;
+    };
+    let already_touching = m
+        .source
+        .and_then(|bug| hive.find_bug(&bug))
+        .is_some_and(touches);
+    touches(destination) & !already_touching
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::alphabeta::{score_from_table, score_to_table, AlphaBeta, WIN};
+    use crate::logic::eval::EvalKind;
+    use crate::logic::game::{Game, GameState};
+    use crate::logic::search::{SearchAlgorithm, SearchSettings};
+
+    fn settings(max_depth: u8) -> SearchSettings {
+        SearchSettings {
+            algorithm: SearchAlgorithm::AlphaBeta,
+            evaluation: EvalKind::QueenPressure,
+            max_depth,
+            hash_size_mb: 1,
+            ..SearchSettings::default()
+        }
+    }
+
+    #[test]
+    fn finds_winning_move() {
+        // Black surrounds the white queen with bA2 -wA3
+        let mut game = Game::from_game_string(
+            "Base;InProgress;Black[8];wS1;bA1 /wS1;wS2 \\wS1;bQ -bA1;wQ wS2/;bS1 bQ\\;wA1 wS2-;\
+             bG1 -bQ;wA2 \\wQ;bB1 -bG1;wB1 -wQ;bG2 bS1\\;wA2 wQ-;bA2 bB1\\;wA3 wQ/",
+        )
+        .unwrap();
+        let m = AlphaBeta::new(&settings(2)).search(&mut game).unwrap();
+        game.try_play_move(m).unwrap();
+        assert_eq!(game.state, GameState::BlackWins);
+    }
+
+    #[test]
+    fn table_win_scores_count_from_the_position() {
+        // Win 5 plies after the root, found in a position 3 plies deep
+        let stored = score_to_table(WIN - 5, 3);
+        assert_eq!(stored, WIN - 2);
+        // The same position reached after a single ply is 3 plies from the win
+        assert_eq!(score_from_table(stored, 1), WIN - 3);
+        assert_eq!(
+            score_from_table(score_to_table(-(WIN - 5), 3), 1),
+            -(WIN - 3)
+        );
+        assert_eq!(score_from_table(score_to_table(42, 3), 1), 42);
+    }
+
+    #[test]
+    fn search_leaves_game_untouched() {
+        let mut game = Game::from_game_string(
+            "Base;InProgress;White[4];wS1;bG1 -wS1;wA1 wS1/;bG2 /bG1;wQ wS1\\;bQ \\bG1",
+        )
+        .unwrap();
+        let mut searched = game.clone();
+        let m = AlphaBeta::new(&settings(3)).search(&mut searched).unwrap();
+        assert!(searched == game);
+        assert!(game.compute_valid_moves().contains(&m));
+    }
+
+    #[test]
+    fn table_is_kept_between_searches() {
+        let mut game = Game::from_game_string(
+            "Base;InProgress;White[4];wS1;bG1 -wS1;wA1 wS1/;bG2 /bG1;wQ wS1\\;bQ \\bG1",
+        )
+        .unwrap();
+        let mut search = AlphaBeta::new(&settings(3));
+        let first = search.analyse(&mut game);
+        let nodes = search.nodes;
+
+        search.set_settings(&settings(3));
+        let second = search.analyse(&mut game);
+        assert_eq!(second.score, first.score);
+        assert!(search.nodes < nodes / 4, "{} {nodes}", search.nodes);
+
+        // A cleared search starts from scratch again, move order varies a little
+        // from one search to another
+        search.clear();
+        search.set_settings(&settings(3));
+        search.analyse(&mut game);
+        assert!(search.nodes > nodes / 2, "{} {nodes}", search.nodes);
+    }
+
+    #[test]
+    fn principal_variation_is_playable() {
+        let mut game = Game::from_game_string(
+            "Base;InProgress;White[4];wS1;bG1 -wS1;wA1 wS1/;bG2 /bG1;wQ wS1\\;bQ \\bG1",
+        )
+        .unwrap();
+        let analysis = AlphaBeta::new(&settings(3)).analyse(&mut game);
+        assert_eq!(analysis.depth, 3);
+        assert!(analysis.principal_variation.first() == analysis.best_move.as_ref());
+        assert!(analysis.principal_variation.len() <= 3);
+        for m in analysis.principal_variation {
+            game.try_play_move(m).unwrap();
+        }
+    }
+}
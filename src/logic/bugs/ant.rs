@@ -1,20 +1,16 @@
-use crate::logic::bugs::bug::Bug;
+use crate::logic::hive::Hive;
 use crate::logic::tile::Tile;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use crate::logic::bugs;
 use pathfinding::prelude::bfs_reach;
 
-fn successors(tile: &Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> Vec<Tile> {
-    bugs::queen::moves(*tile, active_bugs)
-        .iter()
-        .cloned()
-        .collect()
+fn successors(tile: &Tile, hive: &Hive) -> Vec<Tile> {
+    bugs::queen::moves(*tile, hive).iter().cloned().collect()
 }
 
-pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile> {
-    let mut candidates =
-        bfs_reach(tile, |t| successors(&t, active_bugs)).collect::<HashSet<Tile>>();
+pub fn moves(tile: Tile, hive: &Hive) -> HashSet<Tile> {
+    let mut candidates = bfs_reach(tile, |t| successors(t, hive)).collect::<HashSet<Tile>>();
     candidates.remove(&tile);
 
     let mut connected_moves = vec![];
@@ -22,7 +18,7 @@ pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile>
         let occupied_neighbors: Vec<Tile> = m
             .neighbors()
             .iter()
-            .filter(|t| active_bugs.get(t).is_some())
+            .filter(|t| hive.is_occupied(**t))
             .cloned()
             .collect();
 
@@ -7,7 +7,7 @@ pub fn moves(tile: Tile, hive_without_current_bug: &Hive) -> HashSet<Tile> {
     let slide_neighbors: Vec<Tile> = neighbors
         .iter()
         .cloned()
-        .filter(|tile| hive_without_current_bug.get_nearby_bugs(*tile).len() > 0)
+        .filter(|tile| !hive_without_current_bug.get_nearby_bugs(*tile).is_empty())
         .collect();
     HashSet::from_iter(slide_neighbors.iter().cloned())
 }
@@ -1,32 +1,72 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Color {
     Black,
     White,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BugKind {
     Queen,
     Beetle,
     Ant,
     Spider,
     Grasshopper,
+    Mosquito,
+    Ladybug,
+    Pillbug,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Bug {
     pub kind: BugKind,
     pub index: u8,
     pub color: Color,
 }
 
+// Bugs of both colors with every expansion
+pub const N_BUGS: usize = 28;
+
 impl Bug {
     pub fn new(kind: BugKind, index: u8, color: Color) -> Self {
         Self { kind, index, color }
     }
+
+    // Dense index of the bug among N_BUGS, None if it is not part of the piece set
+    pub fn slot(&self) -> Option<usize> {
+        let (offset, count) = match self.kind {
+            BugKind::Queen => (0, 0),
+            BugKind::Spider => (1, 2),
+            BugKind::Beetle => (3, 2),
+            BugKind::Grasshopper => (5, 3),
+            BugKind::Ant => (8, 3),
+            BugKind::Mosquito => (11, 0),
+            BugKind::Ladybug => (12, 0),
+            BugKind::Pillbug => (13, 0),
+        };
+        let index = match (count, self.index) {
+            (0, 0) => 0,
+            (_, index) if (1..=count).contains(&index) => index as usize - 1,
+            _ => return None,
+        };
+        let color_offset = if self.color == Color::White {
+            0
+        } else {
+            N_BUGS / 2
+        };
+        Some(color_offset + offset + index)
+    }
 }
 
 impl Display for Bug {
@@ -37,11 +77,15 @@ impl Display for Bug {
             BugKind::Ant => 'A',
             BugKind::Spider => 'S',
             BugKind::Grasshopper => 'G',
+            BugKind::Mosquito => 'M',
+            BugKind::Ladybug => 'L',
+            BugKind::Pillbug => 'P',
         };
         let color = if self.color == Color::White { 'w' } else { 'b' };
         match self.kind {
-            // Add Mosquito, Ladybug and Pillbug later
-            BugKind::Queen => write!(f, "{color}{letter}"),
+            BugKind::Queen | BugKind::Mosquito | BugKind::Ladybug | BugKind::Pillbug => {
+                write!(f, "{color}{letter}")
+            }
             _ => write!(f, "{color}{letter}{}", self.index),
         }
     }
@@ -54,30 +98,34 @@ impl FromStr for Bug {
     type Err = ParseBugError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let dir_char_set = ["/", "\\", "-"];
-
-        // Should raise Error if direction in string
-        if dir_char_set.iter().any(|c| s.contains(c)) {
-            return Err(ParseBugError);
-        }
+        let chars: Vec<char> = s.chars().collect();
+        let (color, kind, index) = match chars.as_slice() {
+            [color, kind] => (color, kind, None),
+            [color, kind, index] => (color, kind, Some(index)),
+            _ => return Err(ParseBugError),
+        };
 
-        let color = match s.chars().nth(0) {
-            Some('w') => Ok(Color::White),
-            Some('b') => Ok(Color::Black),
+        let color = match color {
+            'w' => Ok(Color::White),
+            'b' => Ok(Color::Black),
             _ => Err(ParseBugError),
         };
 
-        let kind = match s.chars().nth(1) {
-            Some('Q') => Ok(BugKind::Queen),
-            Some('S') => Ok(BugKind::Spider),
-            Some('B') => Ok(BugKind::Beetle),
-            Some('G') => Ok(BugKind::Grasshopper),
-            Some('A') => Ok(BugKind::Ant),
+        let kind = match kind {
+            'Q' => Ok(BugKind::Queen),
+            'S' => Ok(BugKind::Spider),
+            'B' => Ok(BugKind::Beetle),
+            'G' => Ok(BugKind::Grasshopper),
+            'A' => Ok(BugKind::Ant),
+            'M' => Ok(BugKind::Mosquito),
+            'L' => Ok(BugKind::Ladybug),
+            'P' => Ok(BugKind::Pillbug),
             _ => Err(ParseBugError),
         };
 
-        let index = match s.chars().last().expect("Empty string").to_digit(10) {
-            Some(i) => Ok(i as u8),
+        let index = match index.map(|i| i.to_digit(10)) {
+            Some(Some(i)) => Ok(i as u8),
+            Some(None) => Err(ParseBugError),
             None => Ok(0),
         };
 
@@ -0,0 +1,60 @@
+use crate::logic::hive::Hive;
+use crate::logic::tile::Tile;
+use std::collections::HashSet;
+
+pub fn moves(tile: Tile, hive: &Hive) -> HashSet<Tile> {
+    let occupied_neighbors = |t: &Tile| -> Vec<Tile> {
+        t.neighbors()
+            .into_iter()
+            .filter(|n| hive.is_occupied(*n))
+            .collect()
+    };
+
+    // Two steps on top of the hive
+    let mut on_top = HashSet::new();
+    for first_step in occupied_neighbors(&tile) {
+        for second_step in occupied_neighbors(&first_step) {
+            on_top.insert(second_step);
+        }
+    }
+
+    // Then one step down into an empty space
+    let mut candidates = HashSet::new();
+    for t in on_top {
+        for neigh in t.neighbors() {
+            if !hive.is_occupied(neigh) & (neigh != tile) {
+                candidates.insert(neigh);
+            }
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::bugs::ladybug::moves;
+    use crate::logic::hive::Hive;
+    use crate::logic::tile::{Direction, Tile};
+    use std::collections::HashSet;
+
+    #[test]
+    fn two_steps_up_one_step_down() {
+        // Ladybug west of a line of two bugs
+        let origin = Tile::new(0, 0, 0);
+        let first = origin.move_towards(Direction::E, 1);
+        let second = origin.move_towards(Direction::E, 2);
+        let mut hive = Hive::new();
+        hive.add_bug(first, Bug::new(BugKind::Queen, 0, Color::White));
+        hive.add_bug(second, Bug::new(BugKind::Ant, 1, Color::Black));
+
+        // Can only land around the second bug, not next to the first one only
+        let expected: HashSet<Tile> = second
+            .neighbors()
+            .into_iter()
+            .filter(|t| !hive.is_occupied(*t))
+            .collect();
+        assert!(moves(origin, &hive) == expected);
+        assert!(!expected.contains(&origin));
+    }
+}
@@ -2,5 +2,8 @@ pub mod ant;
 pub mod beetle;
 pub mod bug;
 pub mod grasshopper;
+pub mod ladybug;
+pub mod mosquito;
+pub mod pillbug;
 pub mod queen;
 pub mod spider;
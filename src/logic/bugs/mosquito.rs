@@ -0,0 +1,103 @@
+use crate::logic::bugs;
+use crate::logic::bugs::bug::BugKind;
+use crate::logic::hive::Hive;
+use crate::logic::tile::Tile;
+use std::collections::HashSet;
+
+// Kinds of the bugs on top of the stacks touching the tile
+fn touching_kinds(tile: Tile, hive: &Hive) -> HashSet<BugKind> {
+    tile.neighbors()
+        .iter()
+        .filter_map(|t| hive.top_bug(*t))
+        .map(|bug| bug.kind)
+        .collect()
+}
+
+// Touching a pillbug on the ground gives its special ability
+pub fn has_pillbug_ability(tile: Tile, hive: &Hive) -> bool {
+    (hive.stack_height(tile) == 1) & touching_kinds(tile, hive).contains(&BugKind::Pillbug)
+}
+
+// Bugs moving off the hive look at it without the mosquito, which is lifted and put back
+pub fn moves(tile: Tile, hive: &mut Hive) -> HashSet<Tile> {
+    let mosquito = hive.top_bug(tile).expect("Couldn't find the mosquito.");
+    // On top of the hive, moves like a beetle
+    if hive.stack_height(tile) > 1 {
+        return hive.with_bug_lifted(mosquito, |hive| bugs::beetle::moves(tile, hive));
+    }
+
+    let mut candidates = HashSet::new();
+    for kind in touching_kinds(tile, hive) {
+        let kind_moves = match kind {
+            BugKind::Queen => bugs::queen::moves(tile, hive),
+            BugKind::Beetle => {
+                hive.with_bug_lifted(mosquito, |hive| bugs::beetle::moves(tile, hive))
+            }
+            BugKind::Grasshopper => {
+                hive.with_bug_lifted(mosquito, |hive| bugs::grasshopper::moves(tile, hive))
+            }
+            BugKind::Spider => {
+                hive.with_bug_lifted(mosquito, |hive| bugs::spider::moves(tile, hive))
+            }
+            BugKind::Ant => bugs::ant::moves(tile, hive),
+            BugKind::Ladybug => {
+                hive.with_bug_lifted(mosquito, |hive| bugs::ladybug::moves(tile, hive))
+            }
+            BugKind::Pillbug => bugs::pillbug::moves(tile, hive),
+            // Touching only mosquitoes gives no movement
+            BugKind::Mosquito => HashSet::new(),
+        };
+        candidates.extend(kind_moves);
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::bugs::mosquito::moves;
+    use crate::logic::hive::Hive;
+    use crate::logic::tile::{Direction, Tile};
+    use std::collections::HashSet;
+
+    fn hive_with(bugs: &[(Tile, Bug)]) -> Hive {
+        let mut hive = Hive::new();
+        for (tile, bug) in bugs {
+            hive.add_bug(*tile, *bug);
+        }
+        hive
+    }
+
+    #[test]
+    fn copies_grasshopper() {
+        let origin = Tile::new(0, 0, 0);
+        let mosquito = Bug::new(BugKind::Mosquito, 0, Color::White);
+        let grasshopper = Bug::new(BugKind::Grasshopper, 1, Color::Black);
+        let east = origin.move_towards(Direction::E, 1);
+        let mut hive = hive_with(&[(origin, mosquito), (east, grasshopper)]);
+
+        let expected = HashSet::from([origin.move_towards(Direction::E, 2)]);
+        assert!(moves(origin, &mut hive) == expected);
+    }
+
+    #[test]
+    fn touching_only_mosquito_cannot_move() {
+        let origin = Tile::new(0, 0, 0);
+        let white_mosquito = Bug::new(BugKind::Mosquito, 0, Color::White);
+        let black_mosquito = Bug::new(BugKind::Mosquito, 0, Color::Black);
+        let east = origin.move_towards(Direction::E, 1);
+        let mut hive = hive_with(&[(origin, white_mosquito), (east, black_mosquito)]);
+
+        assert!(moves(origin, &mut hive).is_empty());
+    }
+
+    #[test]
+    fn moves_like_beetle_on_top() {
+        let origin = Tile::new(0, 0, 0);
+        let mosquito = Bug::new(BugKind::Mosquito, 0, Color::White);
+        let ant = Bug::new(BugKind::Ant, 1, Color::Black);
+        let mut hive = hive_with(&[(origin, ant), (origin, mosquito)]);
+
+        assert_eq!(moves(origin, &mut hive).len(), 6);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::logic::bugs;
+use crate::logic::bugs::bug::Bug;
+use crate::logic::hive::Hive;
+use crate::logic::tile::Tile;
+use std::collections::HashSet;
+
+pub fn moves(tile: Tile, hive: &Hive) -> HashSet<Tile> {
+    bugs::queen::moves(tile, hive)
+}
+
+// Going over the hive between two neighboring tiles is blocked when both tiles
+// touching them hold stacks
+fn is_upper_gate(source_tile: Tile, target_tile: Tile, hive: &Hive) -> bool {
+    let target_neighbors = target_tile.neighbors();
+    source_tile
+        .neighbors()
+        .iter()
+        .filter(|t| target_neighbors.contains(t))
+        .all(|t| hive.stack_height(*t) > 1)
+}
+
+// Bugs the pillbug on tile can lift over itself, with the tiles they can be dropped on
+pub fn throws(tile: Tile, hive: &Hive, last_moved: Option<Bug>) -> Vec<(Bug, HashSet<Tile>)> {
+    let neighbors = tile.neighbors();
+    let landing_tiles: HashSet<Tile> = neighbors
+        .iter()
+        .filter(|t| !hive.is_occupied(**t))
+        .filter(|t| !is_upper_gate(tile, **t, hive))
+        .cloned()
+        .collect();
+    if landing_tiles.is_empty() {
+        return vec![];
+    }
+
+    let pinned_tiles = hive.pinned_tiles();
+    let mut throws = vec![];
+    for neigh in neighbors {
+        // Only unstacked bugs can be thrown
+        if hive.stack_height(neigh) != 1 || is_upper_gate(neigh, tile, hive) {
+            continue;
+        }
+        let bug = hive.stack(neigh)[0];
+        // The bug just moved by the opponent can't be thrown
+        if Some(bug) == last_moved {
+            continue;
+        }
+        if !pinned_tiles.contains(&neigh) {
+            throws.push((bug, landing_tiles.clone()));
+        }
+    }
+    throws
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::bugs::pillbug::throws;
+    use crate::logic::hive::Hive;
+    use crate::logic::tile::{Direction, Tile};
+
+    #[test]
+    fn throws_adjacent_bug() {
+        // Ant west of the pillbug, queen east of it
+        let origin = Tile::new(0, 0, 0);
+        let pillbug = Bug::new(BugKind::Pillbug, 0, Color::White);
+        let ant = Bug::new(BugKind::Ant, 1, Color::Black);
+        let queen = Bug::new(BugKind::Queen, 0, Color::White);
+        let mut hive = Hive::new();
+        hive.add_bug(origin, pillbug);
+        hive.add_bug(origin.move_towards(Direction::W, 1), ant);
+        hive.add_bug(origin.move_towards(Direction::E, 1), queen);
+
+        let thrown = throws(origin, &hive, None);
+        assert_eq!(thrown.len(), 2);
+        for (_, landing_tiles) in &thrown {
+            assert_eq!(landing_tiles.len(), 4);
+        }
+
+        // The bug moved last turn can't be thrown
+        let thrown = throws(origin, &hive, Some(ant));
+        assert_eq!(thrown.len(), 1);
+        assert!(thrown[0].0 == queen);
+    }
+
+    #[test]
+    fn does_not_break_the_hive() {
+        // Ant is the only link between the pillbug and the queen
+        let origin = Tile::new(0, 0, 0);
+        let pillbug = Bug::new(BugKind::Pillbug, 0, Color::White);
+        let ant = Bug::new(BugKind::Ant, 1, Color::Black);
+        let queen = Bug::new(BugKind::Queen, 0, Color::White);
+        let mut hive = Hive::new();
+        hive.add_bug(origin, pillbug);
+        hive.add_bug(origin.move_towards(Direction::E, 1), ant);
+        hive.add_bug(origin.move_towards(Direction::E, 2), queen);
+
+        assert!(throws(origin, &hive, None).is_empty());
+    }
+}
@@ -1,8 +1,8 @@
-use crate::logic::bugs::bug::Bug;
+use crate::logic::hive::Hive;
 use crate::logic::tile::{Direction, Tile};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
-fn is_gate(source_tile: Tile, target_tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> bool {
+fn is_gate(source_tile: Tile, target_tile: Tile, hive: &Hive) -> bool {
     let delta = target_tile - source_tile;
     let direction = match delta {
         Tile { q: -1, r: 0, s: 1 } => Direction::W,
@@ -43,22 +43,22 @@ fn is_gate(source_tile: Tile, target_tile: Tile, active_bugs: &HashMap<Tile, Vec
         ),
     };
 
-    active_bugs.get(&tile_a).is_some() & active_bugs.get(&tile_b).is_some()
+    hive.is_occupied(tile_a) & hive.is_occupied(tile_b)
 }
 
-pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile> {
+pub fn moves(tile: Tile, hive: &Hive) -> HashSet<Tile> {
     // Free neighbors of queen
     let neighbors = tile.neighbors();
 
     let free_neighbors_vec: Vec<Tile> = neighbors
         .iter()
-        .filter(|tile| active_bugs.get(tile).is_none())
+        .filter(|tile| !hive.is_occupied(**tile))
         .cloned()
         .collect();
 
     let occupied_neighbors: Vec<Tile> = neighbors
         .iter()
-        .filter(|tile| active_bugs.get(tile).is_some())
+        .filter(|tile| hive.is_occupied(**tile))
         .cloned()
         .collect();
 
@@ -73,7 +73,7 @@ pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile>
 
     let candidate_no_gates = neighbors_of_neighbors_set
         .intersection(&free_neighbors_set)
-        .filter(|target| !is_gate(tile, **target, active_bugs))
+        .filter(|target| !is_gate(tile, **target, hive))
         .cloned()
         .collect();
 
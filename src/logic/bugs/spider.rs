@@ -1,9 +1,9 @@
 use crate::logic::bugs;
-use crate::logic::bugs::bug::Bug;
+use crate::logic::hive::Hive;
 use crate::logic::tile::Tile;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
-pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile> {
+pub fn moves(tile: Tile, hive: &Hive) -> HashSet<Tile> {
     let mut tiles = HashSet::new();
     let mut iteration = HashSet::new();
     let mut visited = HashSet::new();
@@ -12,9 +12,9 @@ pub fn moves(tile: Tile, active_bugs: &HashMap<Tile, Vec<Bug>>) -> HashSet<Tile>
     for idx in 0..3 {
         iteration = HashSet::new();
         for t in &tiles {
-            let queen_moves = bugs::queen::moves(*t, active_bugs);
+            let queen_moves = bugs::queen::moves(*t, hive);
             for st in queen_moves {
-                if visited.get(&st).is_none() {
+                if !visited.contains(&st) {
                     iteration.insert(st);
                 }
                 if idx < 2 {
@@ -1,13 +1,46 @@
+use crate::logic::bugs::bug::{Bug, BugKind, Color};
 use crate::logic::game::Game;
+use crate::logic::hive::Hive;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EvalKind {
+    // Number of bugs of the player to move in the hive
+    PieceCount,
+    // Bugs around the opponent queen minus bugs around our own queen
+    QueenPressure,
+}
+
+// Occupied tiles around the queen of the given color
+fn queen_pressure(hive: &Hive, color: Color) -> i32 {
+    let queen = Bug::new(BugKind::Queen, 0, color);
+    match hive.find_bug(&queen) {
+        Some(tile) => tile
+            .neighbors()
+            .iter()
+            .filter(|t| hive.is_occupied(**t))
+            .count() as i32,
+        None => 0,
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Eval {
+    pub kind: EvalKind,
+}
 
-pub(crate) struct Eval;
 impl minimax::Evaluator for Eval {
     type G = Game;
     fn evaluate(&self, state: &Game) -> minimax::Evaluation {
-        state
-            .get_hive()
-            .as_ref()
-            .expect("Couldn't get hive.")
-            .count_bugs_of_color(state.turn_color) as minimax::Evaluation
+        let hive = state.get_hive().as_ref().expect("Couldn't get hive.");
+        let piece_count = hive.count_bugs_of_color(state.turn_color);
+        let score = match self.kind {
+            EvalKind::PieceCount => piece_count,
+            EvalKind::QueenPressure => {
+                let pressure = queen_pressure(hive, state.turn_color.opposite())
+                    - queen_pressure(hive, state.turn_color);
+                10 * pressure + piece_count
+            }
+        };
+        score as minimax::Evaluation
     }
 }
@@ -1,25 +1,133 @@
+use crate::error::HiveError;
 use crate::logic::bugs::bug::{Bug, Color};
-use crate::logic::eval;
 use crate::logic::hive::Hive;
 use crate::logic::player::Player;
 use crate::logic::r#move::Move;
-use minimax::{Strategy, Winner};
+use crate::logic::search;
+use crate::logic::search::SearchSettings;
+use crate::logic::tile::Tile;
+use crate::logic::zobrist;
+use minimax::Winner;
+use regex::Regex;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-#[derive(Debug, Clone, PartialEq)]
+thread_local! {
+    // Hive the moves of a shared game are looked for on, one per thread
+    static SCRATCH_HIVE: RefCell<Hive> = RefCell::new(Hive::new());
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum GameState {
     NotStarted,
     InProgress,
     WhiteWins,
     BlackWins,
+    Draw,
+}
+
+impl FromStr for GameState {
+    type Err = HiveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NotStarted" => Ok(GameState::NotStarted),
+            "InProgress" => Ok(GameState::InProgress),
+            "WhiteWins" => Ok(GameState::WhiteWins),
+            "BlackWins" => Ok(GameState::BlackWins),
+            "Draw" => Ok(GameState::Draw),
+            _ => Err(HiveError::Parse(format!("invalid game state {s}"))),
+        }
+    }
+}
+
+// Base game plus any of the Mosquito, Ladybug and Pillbug expansions
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct GameType {
+    mosquito: bool,
+    ladybug: bool,
+    pillbug: bool,
+}
+
+const EXPANSIONS: [char; 3] = ['M', 'L', 'P'];
+
+impl GameType {
+    pub fn has_mosquito(&self) -> bool {
+        self.mosquito
+    }
+
+    pub fn has_ladybug(&self) -> bool {
+        self.ladybug
+    }
+
+    pub fn has_pillbug(&self) -> bool {
+        self.pillbug
+    }
+}
+
+impl FromStr for GameType {
+    type Err = HiveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || HiveError::UnsupportedGameType(s.to_string());
+        let expansions = match s.split_once('+') {
+            None if s == "Base" => return Ok(GameType::default()),
+            Some(("Base", expansions)) if !expansions.is_empty() => expansions,
+            _ => return Err(invalid()),
+        };
+
+        // Expansions have to be listed once each, in canonical order
+        let mut remaining = EXPANSIONS.iter();
+        let mut gtype = GameType::default();
+        for c in expansions.chars() {
+            match remaining.find(|&&e| e == c) {
+                Some('M') => gtype.mosquito = true,
+                Some('L') => gtype.ladybug = true,
+                Some('P') => gtype.pillbug = true,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(gtype)
+    }
+}
+
+impl Display for GameType {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let expansions: String = EXPANSIONS
+            .iter()
+            .zip([self.mosquito, self.ladybug, self.pillbug])
+            .filter(|(_, enabled)| *enabled)
+            .map(|(c, _)| c)
+            .collect();
+        if expansions.is_empty() {
+            write!(f, "Base")
+        } else {
+            write!(f, "Base+{expansions}")
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
-enum GameType {
-    Base,
+// Optional rules ending endless games
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct DrawRules {
+    // Draw when the same position occurs for the third time
+    pub repetition: bool,
+    // Draw once both players played that many turns, 0 for no limit
+    pub max_turns: u32,
 }
 
-#[derive(Clone)]
+// Everything needed to take a move back, returned by Game::make_move
+#[derive(Clone, Copy, PartialEq)]
+pub struct UndoInfo {
+    m: Move,
+    // Tile the bug was on before the move, None if it was placed
+    from: Option<Tile>,
+    // Game state before the move
+    state: GameState,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Game {
     pub state: GameState,
     gtype: GameType,
@@ -27,26 +135,123 @@ pub struct Game {
     pub(crate) turn_color: Color,
     players: [Player; 2],
     hive: Option<Hive>,
-    moves_history: Vec<Move>,
+    moves_history: Vec<UndoInfo>,
+    // Hash of every position reached, starting with the empty hive
+    position_history: Vec<u64>,
+    draw_rules: DrawRules,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        Game {
+        Game::with_game_type(GameType::default())
+    }
+
+    pub fn with_game_type(gtype: GameType) -> Self {
+        let mut game = Game {
             state: GameState::NotStarted,
-            gtype: GameType::Base,
             turn_number: 0,
             turn_color: Color::White,
-            players: [Player::new(Color::White), Player::new(Color::Black)],
+            players: [
+                Player::new(Color::White, &gtype),
+                Player::new(Color::Black, &gtype),
+            ],
+            gtype,
             hive: Some(Hive::new()),
             moves_history: vec![],
+            position_history: vec![],
+            draw_rules: DrawRules::default(),
+        };
+        game.position_history.push(game.position_hash());
+        game
+    }
+
+    pub fn from_game_string(game_string: &str) -> Result<Game, HiveError> {
+        Game::load(game_string, DrawRules::default())
+    }
+
+    // Build a game from a GameTypeString or a full GameString, replaying its moves
+    pub fn load(game_string: &str, draw_rules: DrawRules) -> Result<Game, HiveError> {
+        let mut fields = game_string.split(';');
+        let gtype = GameType::from_str(fields.next().unwrap_or_default())?;
+        let mut game = Game::with_game_type(gtype);
+        game.set_state(GameState::InProgress);
+        game.set_draw_rules(draw_rules);
+
+        let (state, turn) = match (fields.next(), fields.next()) {
+            (None, _) => return Ok(game),
+            (Some(state), Some(turn)) => (GameState::from_str(state)?, turn),
+            _ => {
+                return Err(HiveError::Parse(format!(
+                    "invalid game string {game_string}"
+                )))
+            }
+        };
+        let turn_regex = Regex::new(r"^(White|Black)\[\d+\]$").unwrap();
+        if !turn_regex.is_match(turn) {
+            return Err(HiveError::Parse(format!("invalid turn string {turn}")));
         }
+
+        for move_str in fields {
+            // The game string itself is wrong, not a move sent on its own
+            let n = game.n_moves() + 1;
+            let invalid = |e: HiveError| HiveError::Parse(format!("{e} at move {n}"));
+            let m = Move::from_str(move_str)
+                .map_err(|_| invalid(HiveError::InvalidMove(move_str.to_string())))?;
+            game.try_play_move(m).map_err(invalid)?;
+        }
+
+        if game.turn_string() != turn {
+            return Err(HiveError::Parse(format!(
+                "turn string {turn} doesn't match moves, expected {}",
+                game.turn_string()
+            )));
+        }
+        let not_started = (game.n_moves() == 0) & (state == GameState::NotStarted);
+        if (game.state != state) & !not_started {
+            return Err(HiveError::Parse(format!(
+                "game state {state:?} doesn't match moves, expected {:?}",
+                game.state
+            )));
+        }
+        Ok(game)
     }
 
     pub fn set_state(&mut self, state: GameState) {
         self.state = state
     }
 
+    pub fn set_draw_rules(&mut self, draw_rules: DrawRules) {
+        self.draw_rules = draw_rules
+    }
+
+    // Zobrist key of the hive and the player to move
+    pub fn position_hash(&self) -> u64 {
+        let hive_key = self
+            .hive
+            .as_ref()
+            .expect("Couldn't get hive.")
+            .position_hash();
+        match self.turn_color {
+            Color::White => hive_key,
+            Color::Black => hive_key ^ zobrist::BLACK_TO_MOVE,
+        }
+    }
+
+    // Number of times the current position occurred, including this one
+    pub fn position_occurrences(&self) -> usize {
+        let current = self.position_hash();
+        self.position_history
+            .iter()
+            .filter(|&&hash| hash == current)
+            .count()
+    }
+
     pub fn get_hive(&self) -> &Option<Hive> {
         &self.hive
     }
@@ -54,48 +259,123 @@ impl Game {
     pub fn update_game_state(&mut self) {
         let white_queen = &Bug::from_str("wQ").expect("Couldn't create bug from string.");
         let black_queen = &Bug::from_str("bQ").expect("Couldn't create bug from string.");
-        let in_game_white_queen = self.hive.as_ref().unwrap().find_bug(white_queen);
-        let in_game_black_queen = self.hive.as_ref().unwrap().find_bug(black_queen);
+        let hive = self.hive.as_ref().unwrap();
+        let is_queen_surrounded = |queen| {
+            hive.find_bug(queen)
+                .is_some_and(|tile| hive.is_surrounded(tile))
+        };
 
-        if let Some(tile) = in_game_white_queen {
-            if self.hive.as_ref().unwrap().is_surrounded(tile) {
-                self.state = GameState::BlackWins;
-            }
-        }
-        if let Some(tile) = in_game_black_queen {
-            if self.hive.as_ref().unwrap().is_surrounded(tile) {
-                self.state = GameState::WhiteWins
+        // Both queens can get surrounded by the same move
+        match (
+            is_queen_surrounded(white_queen),
+            is_queen_surrounded(black_queen),
+        ) {
+            (true, true) => self.state = GameState::Draw,
+            (true, false) => self.state = GameState::BlackWins,
+            (false, true) => self.state = GameState::WhiteWins,
+            (false, false) => {
+                let repeated = self.draw_rules.repetition & (self.position_occurrences() >= 3);
+                let max_turns = self.draw_rules.max_turns;
+                let too_long = (max_turns > 0) & (self.turn_number >= 2 * max_turns);
+                if repeated | too_long {
+                    self.state = GameState::Draw;
+                }
             }
         }
     }
 
     pub fn play_move(&mut self, m: Move) {
-        self.hive
-            .as_mut()
-            .expect("Couldn't find hive.")
-            .play_move(m);
+        let hive = self.hive.as_mut().expect("Couldn't find hive.");
+        let entry = UndoInfo {
+            m,
+            from: m.source.and_then(|source| hive.find_bug(&source)),
+            state: self.state,
+        };
+        hive.play_move(m);
 
-        let player = match self.turn_color {
-            Color::White => self.players.get_mut(0),
-            Color::Black => self.players.get_mut(1),
+        if let Some(source) = m.source {
+            let player = self.get_current_player_mut();
+            if player.is_piece_inactive(source) {
+                player.set_piece_active(source);
+            }
         }
-        .expect("Couldn't get active player");
 
-        if player.is_piece_inactive(m.source) {
-            player.set_piece_active(m.source);
+        self.turn_number += 1;
+        self.turn_color = self.turn_color.opposite();
+        self.moves_history.push(entry);
+        self.position_history.push(self.position_hash());
+    }
+
+    // Play a move after checking it is legal in the current position
+    pub fn try_play_move(&mut self, m: Move) -> Result<(), HiveError> {
+        if self.state != GameState::InProgress {
+            return Err(HiveError::GameOver);
         }
+        let m = self
+            .normalize_move(m)
+            .ok_or(HiveError::InvalidMove(m.to_string()))?;
+        self.make_move(m);
+        Ok(())
+    }
 
-        self.turn_number += 1;
-        self.turn_color = if self.turn_color == Color::White {
-            Color::Black
-        } else {
-            Color::White
-        };
-        self.moves_history.push(m);
+    // Play a move in place, the returned info takes it back with unmake_move
+    pub fn make_move(&mut self, m: Move) -> UndoInfo {
+        self.play_move(m);
+        self.update_game_state();
+        *self
+            .moves_history
+            .last()
+            .expect("Couldn't get played move.")
+    }
+
+    // Valid move putting the same bug on the same tile as m, as written by the
+    // engine, a move can reference any bug next to its destination
+    pub fn normalize_move(&mut self, m: Move) -> Option<Move> {
+        let valid_moves = self.compute_valid_moves();
+        let hive = self.hive.as_ref().expect("Couldn't get hive.");
+        let destination = hive.move_destination(&m);
+        valid_moves.into_iter().find(|valid| {
+            (valid.source == m.source) & (hive.move_destination(valid) == destination)
+        })
+    }
+
+    // Take back a move returned by make_move, it has to be the last one played
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        let last = self.moves_history.pop();
+        debug_assert!(last == Some(undo), "Only the last move can be unmade.");
+        self.position_history.pop();
+        self.turn_number -= 1;
+        self.turn_color = self.turn_color.opposite();
+        self.state = undo.state;
+
+        if let Some(source) = undo.m.source {
+            let hive = self.hive.as_mut().expect("Couldn't find hive.");
+            hive.remove_bug(source);
+            match undo.from {
+                Some(tile) => hive.add_bug(tile, source),
+                None => self.get_current_player_mut().set_piece_inactive(source),
+            }
+        }
+    }
+
+    // Take back the last move played, returns it if any
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let undo = *self.moves_history.last()?;
+        self.unmake_move(undo);
+        Some(undo.m)
+    }
+
+    // Bug moved or placed on the last turn, it can't move nor be thrown this turn
+    pub fn last_moved_bug(&self) -> Option<Bug> {
+        self.moves_history.last().and_then(|entry| entry.m.source)
+    }
+
+    pub fn n_moves(&self) -> usize {
+        self.moves_history.len()
     }
 
-    pub fn get_best_move(&self) -> Option<Move> {
-        minimax::Negamax::new(eval::Eval, 3).choose_move(&self)
+    pub fn get_best_move(&self, settings: &SearchSettings) -> Option<Move> {
+        search::best_move(self, settings)
     }
 
     pub fn turn_string(&self) -> String {
@@ -104,39 +384,85 @@ impl Game {
         } else {
             "Black"
         };
-        format!("{color}[{}]", self.turn_number)
+        format!("{color}[{}]", self.turn_number / 2 + 1)
     }
 
     pub fn game_string(&self) -> String {
         // GameTypeString;GameStateString;TurnString
-        format!("{:?};{:?};{}", self.gtype, self.state, self.turn_string())
+        format!("{};{:?};{}", self.gtype, self.state, self.turn_string())
+    }
+
+    // GameString followed by the moves played so far
+    pub fn full_game_string(&self) -> String {
+        let moves_string = self.moves_string();
+        if moves_string.is_empty() {
+            self.game_string()
+        } else {
+            format!("{};{moves_string}", self.game_string())
+        }
     }
 
     pub fn moves_string(&self) -> String {
         let mut moves_string = vec![];
-        for m in &self.moves_history {
-            moves_string.push(format!("{m}"));
+        for entry in &self.moves_history {
+            moves_string.push(format!("{}", entry.m));
         }
         moves_string.join(";")
     }
 
-    pub fn compute_valid_moves(&self) -> Vec<Move> {
-        let current_player = self.get_current_player();
-        current_player.valid_moves(
-            &self.hive.as_ref().expect("Couldn't get hive."),
+    // Moves are looked for in place, the game is left as it was
+    pub fn compute_valid_moves(&mut self) -> Vec<Move> {
+        let mut hive = self.hive.take().expect("Couldn't get hive.");
+        let moves = self.valid_moves_on(&mut hive);
+        self.hive = Some(hive);
+        moves
+    }
+
+    // Same moves for a shared game, looked for on a copy of its bugs kept by the thread
+    pub fn valid_moves(&self) -> Vec<Move> {
+        SCRATCH_HIVE.with(|scratch| {
+            let mut hive = scratch.borrow_mut();
+            hive.copy_from(self.hive.as_ref().expect("Couldn't get hive."));
+            self.valid_moves_on(&mut hive)
+        })
+    }
+
+    // Moves of the player to move on a hive holding the game bugs
+    fn valid_moves_on(&self, hive: &mut Hive) -> Vec<Move> {
+        let player_index = match self.turn_color {
+            Color::White => 0,
+            Color::Black => 1,
+        };
+        let moves = self.players[player_index].valid_moves(
+            hive,
             self.turn_number,
             self.turn_color,
-        )
+            self.last_moved_bug(),
+        );
+        // A player with no legal move has to pass
+        if moves.is_empty() {
+            vec![Move::pass()]
+        } else {
+            moves
+        }
     }
 
     pub fn get_current_player(&self) -> &Player {
         let current_player = match self.turn_color {
-            Color::White => self.players.get(0),
+            Color::White => self.players.first(),
             Color::Black => self.players.get(1),
         }
         .expect("Couldn't get player.");
         current_player
     }
+
+    fn get_current_player_mut(&mut self) -> &mut Player {
+        match self.turn_color {
+            Color::White => self.players.get_mut(0),
+            Color::Black => self.players.get_mut(1),
+        }
+        .expect("Couldn't get active player")
+    }
 }
 
 impl minimax::Game for Game {
@@ -144,27 +470,542 @@ impl minimax::Game for Game {
     type M = Move;
 
     fn generate_moves(state: &Game, moves: &mut Vec<Self::M>) {
-        let current_player = state.get_current_player();
-        let valid_moves = current_player.valid_moves(
-            &state.hive.as_ref().expect("Couldn't get hive."),
-            state.turn_number,
-            state.turn_color,
-        );
-        for m in valid_moves {
-            moves.push(m);
-        }
+        moves.append(&mut state.valid_moves());
     }
 
+    // Played in place, the search always calls undo afterwards
     fn apply(state: &mut Self::S, m: Self::M) -> Option<Self::S> {
-        state.play_move(m);
-        Some(state.clone())
+        state.make_move(m);
+        None
+    }
+
+    fn undo(state: &mut Self::S, _m: Self::M) {
+        state.undo_move();
+    }
+
+    fn zobrist_hash(state: &Self::S) -> u64 {
+        state.position_hash()
     }
 
     fn get_winner(state: &Self::S) -> Option<Winner> {
-        match state.state {
-            GameState::WhiteWins => Some(Winner::PlayerJustMoved),
-            GameState::BlackWins => Some(Winner::PlayerJustMoved),
-            _ => None,
+        // turn_color is the player to move, the other one just moved
+        let winner_color = match state.state {
+            GameState::WhiteWins => Color::White,
+            GameState::BlackWins => Color::Black,
+            GameState::Draw => return Some(Winner::Draw),
+            // Going back to an earlier position leads nowhere
+            _ if state.position_occurrences() > 1 => return Some(Winner::Draw),
+            _ => return None,
+        };
+        if winner_color == state.turn_color {
+            Some(Winner::PlayerToMove)
+        } else {
+            Some(Winner::PlayerJustMoved)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::HiveError;
+    use crate::logic::bugs::bug::{Bug, Color};
+    use crate::logic::game::{DrawRules, Game, GameState, GameType};
+    use crate::logic::r#move::Move;
+    use crate::logic::tile::{Direction, Tile};
+    use crate::logic::zobrist;
+    use minimax::Winner;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::str::FromStr;
+
+    impl Game {
+        fn reloads_identically(&self) -> bool {
+            let loaded = Game::from_game_string(&self.full_game_string()).unwrap();
+            loaded == *self
+        }
+    }
+
+    fn new_game() -> Game {
+        let mut game = Game::new();
+        game.set_state(GameState::InProgress);
+        game
+    }
+
+    // Play up to n_moves random valid moves, stopping early if the game ends
+    fn play_random_moves(game: &mut Game, rng: &mut StdRng, n_moves: usize) -> usize {
+        let mut played = 0;
+        while played < n_moves && game.state == GameState::InProgress {
+            let valid_moves = game.compute_valid_moves();
+            if valid_moves.is_empty() {
+                break;
+            }
+            let m = valid_moves[rng.gen_range(0..valid_moves.len())];
+            game.make_move(m);
+            played += 1;
+        }
+        played
+    }
+
+    #[test]
+    fn undo_on_new_game() {
+        let mut game = new_game();
+        assert!(game.undo_move().is_none());
+        assert!(game == new_game());
+    }
+
+    #[test]
+    fn pass_changes_turn_only() {
+        let mut game = new_game();
+        game.play_move(Move::pass());
+        assert!(game.turn_color == Color::Black);
+        assert_eq!(game.get_hive().as_ref().unwrap().get_n_tiles(), 0);
+        assert_eq!(game.moves_string(), "pass");
+        assert!(game.undo_move().unwrap().is_pass());
+        assert!(game == new_game());
+    }
+
+    #[test]
+    fn turn_string_counts_full_turns() {
+        let mut game = new_game();
+        assert_eq!(game.turn_string(), "White[1]");
+        game.play_move(Move::pass());
+        assert_eq!(game.turn_string(), "Black[1]");
+        game.play_move(Move::pass());
+        assert_eq!(game.turn_string(), "White[2]");
+    }
+
+    #[test]
+    fn load_game_string() {
+        let game =
+            Game::from_game_string("Base;InProgress;White[3];wS1;bG1 -wS1;wA1 wS1/;bG2 /bG1")
+                .unwrap();
+        assert_eq!(game.n_moves(), 4);
+        assert_eq!(game.turn_string(), "White[3]");
+        assert!(game.reloads_identically());
+    }
+
+    #[test]
+    fn load_game_type_string_only() {
+        let game = Game::from_game_string("Base").unwrap();
+        assert!(game == new_game());
+        assert!(Game::from_game_string("Base;NotStarted;White[1]").unwrap() == new_game());
+    }
+
+    #[test]
+    fn mosquito_only_in_base_m() {
+        let has_mosquito = |mut game: Game| {
+            game.compute_valid_moves()
+                .iter()
+                .any(|m| m.to_string() == "wM")
+        };
+        assert!(!has_mosquito(Game::from_game_string("Base").unwrap()));
+        assert!(has_mosquito(Game::from_game_string("Base+M").unwrap()));
+        let game = Game::from_game_string("Base+M;InProgress;White[2];wM;bM wM-").unwrap();
+        assert!(game.reloads_identically());
+    }
+
+    #[test]
+    fn ladybug_game_string() {
+        let mut game = Game::from_game_string(
+            "Base+L;InProgress;White[4];wS1;bS1 wS1-;wQ -wS1;bQ bS1-;wL -wQ;bA1 bQ-",
+        )
+        .unwrap();
+        let ladybug = Bug::from_str("wL").unwrap();
+        // Climbs over wQ and wS1 and lands around wS1
+        let n_ladybug_moves = game
+            .compute_valid_moves()
+            .iter()
+            .filter(|m| m.source == Some(ladybug))
+            .count();
+        assert_eq!(n_ladybug_moves, 4);
+        assert!(game.reloads_identically());
+    }
+
+    // Surround both queens, placed next to each other, with bugs of the given color
+    fn surround_queens(game: &mut Game, surround_white: bool, surround_black: bool) {
+        let hive = game.hive.as_mut().unwrap();
+        let white_tile = Tile::new(0, 0, 0);
+        let black_tile = white_tile.move_towards(Direction::E, 1);
+        hive.add_bug(white_tile, Bug::from_str("wQ").unwrap());
+        hive.add_bug(black_tile, Bug::from_str("bQ").unwrap());
+        let mut fillers = [
+            "wA1", "wA2", "wA3", "wG1", "wG2", "wG3", "bA1", "bA2", "bA3", "bG1", "bG2", "bG3",
+        ]
+        .iter()
+        .map(|b| Bug::from_str(b).unwrap());
+        for (tile, surround, away) in [
+            (white_tile, surround_white, Direction::W),
+            (black_tile, surround_black, Direction::E),
+        ] {
+            for t in tile.neighbors() {
+                // Leave the far side free if the queen shouldn't be surrounded
+                let keep_free = !surround & (t == tile.move_towards(away, 1));
+                if hive.get_bugs_on_tile(t).is_none() & !keep_free {
+                    hive.add_bug(t, fillers.next().unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn both_queens_surrounded_is_a_draw() {
+        for (surround_white, surround_black, expected) in [
+            (true, true, GameState::Draw),
+            (true, false, GameState::BlackWins),
+            (false, true, GameState::WhiteWins),
+            (false, false, GameState::InProgress),
+        ] {
+            let mut game = new_game();
+            surround_queens(&mut game, surround_white, surround_black);
+            game.update_game_state();
+            assert_eq!(game.state, expected);
+        }
+        assert!(GameState::from_str("Draw").unwrap() == GameState::Draw);
+    }
+
+    #[test]
+    fn draw_winner_for_search() {
+        let mut game = new_game();
+        game.set_state(GameState::Draw);
+        assert!(matches!(
+            <Game as minimax::Game>::get_winner(&game),
+            Some(Winner::Draw)
+        ));
+        // White just moved and won
+        game.turn_color = Color::Black;
+        game.set_state(GameState::WhiteWins);
+        assert!(matches!(
+            <Game as minimax::Game>::get_winner(&game),
+            Some(Winner::PlayerJustMoved)
+        ));
+    }
+
+    // Play the valid move bringing the bug to the tile
+    fn play_to_tile(game: &mut Game, bug_str: &str, tile: Tile) {
+        let bug = Bug::from_str(bug_str).unwrap();
+        let m = game
+            .compute_valid_moves()
+            .into_iter()
+            .find(|m| {
+                let mut after = game.clone();
+                after.play_move(*m);
+                (m.source == Some(bug)) & (after.hive.unwrap().find_bug(&bug) == Some(tile))
+            })
+            .unwrap();
+        game.try_play_move(m).unwrap();
+    }
+
+    // Both queens step aside and come back
+    fn shuffle_queens(game: &mut Game) {
+        let white_queen_tile = Tile::new(-1, 0, 1);
+        let black_queen_tile = Tile::new(2, 0, -2);
+        play_to_tile(game, "wQ", white_queen_tile.move_towards(Direction::NE, 1));
+        play_to_tile(game, "bQ", black_queen_tile.move_towards(Direction::NW, 1));
+        play_to_tile(game, "wQ", white_queen_tile);
+        play_to_tile(game, "bQ", black_queen_tile);
+    }
+
+    const QUEENS_OUT: &str = "Base;InProgress;White[3];wS1;bS1 wS1-;wQ -wS1;bQ bS1-";
+
+    #[test]
+    fn threefold_repetition_draw() {
+        let draw_rules = DrawRules {
+            repetition: true,
+            max_turns: 0,
+        };
+        let mut game = Game::load(QUEENS_OUT, draw_rules).unwrap();
+        shuffle_queens(&mut game);
+        assert_eq!(game.position_occurrences(), 2);
+        assert_eq!(game.state, GameState::InProgress);
+        shuffle_queens(&mut game);
+        assert_eq!(game.position_occurrences(), 3);
+        assert_eq!(game.state, GameState::Draw);
+
+        // Only when the rule is enabled
+        let mut game = Game::from_game_string(QUEENS_OUT).unwrap();
+        shuffle_queens(&mut game);
+        shuffle_queens(&mut game);
+        assert_eq!(game.state, GameState::InProgress);
+        assert!(matches!(
+            <Game as minimax::Game>::get_winner(&game),
+            Some(Winner::Draw)
+        ));
+    }
+
+    #[test]
+    fn max_turns_draw() {
+        let draw_rules = DrawRules {
+            repetition: false,
+            max_turns: 3,
+        };
+        let mut game = Game::load(QUEENS_OUT, draw_rules).unwrap();
+        play_to_tile(&mut game, "wQ", Tile::new(0, -1, 1));
+        assert_eq!(game.state, GameState::InProgress);
+        play_to_tile(&mut game, "bQ", Tile::new(2, -1, -1));
+        assert_eq!(game.state, GameState::Draw);
+        assert!(game.undo_move().is_some());
+        assert_eq!(game.state, GameState::InProgress);
+    }
+
+    #[test]
+    fn game_type_strings() {
+        for gtype_str in [
+            "Base", "Base+M", "Base+L", "Base+P", "Base+ML", "Base+MP", "Base+LP", "Base+MLP",
+        ] {
+            let gtype = GameType::from_str(gtype_str).unwrap();
+            assert_eq!(gtype.to_string(), gtype_str);
+        }
+        let all = GameType::from_str("Base+MLP").unwrap();
+        assert!(all.has_mosquito() & all.has_ladybug() & all.has_pillbug());
+
+        for invalid in [
+            "", "Base+", "Base+X", "Base+LM", "Base+MM", "base", "Base+M+L", "Expert",
+        ] {
+            assert!(GameType::from_str(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn all_expansions_random_games() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            let played = play_random_moves(&mut game, &mut rng, 40);
+            assert!(game.reloads_identically(), "seed {seed}");
+            for _ in 0..played {
+                game.undo_move();
+            }
+            assert!(
+                game == Game::from_game_string("Base+MLP").unwrap(),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn load_game_string_errors() {
+        assert!(Game::from_game_string("Base+X").is_err());
+        assert!(Game::from_game_string("Base;InProgress").is_err());
+        assert!(Game::from_game_string("Base;InProgress;White[2];wS1").is_err());
+        assert!(Game::from_game_string("Base;WhiteWins;Black[1];wS1").is_err());
+        let err = Game::from_game_string("Base;InProgress;White[2];wS1;bG1 -wS1;wQ -bG1")
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "invalid move wQ -bG1 at move 3");
+    }
+
+    #[test]
+    fn pillbug_throws_neighbor() {
+        let mut game =
+            Game::from_game_string("Base+P;InProgress;White[3];wP;bS1 wP-;wQ -wP;bA1 bS1-")
+                .unwrap();
+        let valid_moves: Vec<String> = game
+            .compute_valid_moves()
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        // wQ can only get behind the pillbug by being thrown, bS1 is pinned by bA1
+        assert!(valid_moves.contains(&"wQ wP\\".to_string()));
+        assert!(!valid_moves.iter().any(|m| m.starts_with("bS1")));
+    }
+
+    #[test]
+    fn last_moved_bug_is_frozen() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+P").unwrap();
+            for _ in 0..40 {
+                if play_random_moves(&mut game, &mut rng, 1) == 0 {
+                    break;
+                }
+                let last_moved = game.last_moved_bug();
+                assert!(game
+                    .compute_valid_moves()
+                    .iter()
+                    .all(|m| last_moved.is_none() || m.source != last_moved));
+            }
+            assert!(game.reloads_identically(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn load_random_game_strings() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = new_game();
+            play_random_moves(&mut game, &mut rng, 30);
+            assert!(game.reloads_identically(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn undo_random_games_back_to_start() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = new_game();
+            let played = play_random_moves(&mut game, &mut rng, 40);
+            for _ in 0..played {
+                assert!(game.undo_move().is_some());
+            }
+            assert!(game == new_game(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn undo_restores_intermediate_positions() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = new_game();
+            play_random_moves(&mut game, &mut rng, 20);
+            let checkpoint = game.clone();
+            let played = play_random_moves(&mut game, &mut rng, 20);
+            for _ in 0..played {
+                game.undo_move();
+            }
+            assert!(game == checkpoint, "seed {seed}");
+            assert_eq!(game.moves_string(), checkpoint.moves_string());
+            assert_eq!(game.game_string(), checkpoint.game_string());
+        }
+    }
+
+    #[test]
+    fn incremental_key_matches_recomputed_key() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            let key_matches = |game: &Game| {
+                let hive = game.get_hive().as_ref().unwrap();
+                hive.position_hash() == zobrist::hive_key(hive)
+            };
+            let mut played = 0;
+            while play_random_moves(&mut game, &mut rng, 1) == 1 {
+                assert!(key_matches(&game), "seed {seed}");
+                played += 1;
+                if played == 50 {
+                    break;
+                }
+            }
+            for _ in 0..played {
+                game.undo_move();
+                assert!(key_matches(&game), "seed {seed}");
+            }
+            assert_eq!(game.get_hive().as_ref().unwrap().position_hash(), 0);
+        }
+    }
+
+    #[test]
+    fn unmake_every_move_restores_game() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            play_random_moves(&mut game, &mut rng, 20 + seed as usize);
+            let before = game.clone();
+            for m in game.compute_valid_moves() {
+                let undo = game.make_move(m);
+                assert!(game.turn_color != before.turn_color);
+                game.unmake_move(undo);
+                assert!(game == before, "seed {seed} move {m}");
+            }
+        }
+    }
+
+    #[test]
+    fn shared_game_moves_match_moves_in_place() {
+        let sorted = |moves: Vec<Move>| {
+            let mut moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+            moves.sort();
+            moves
+        };
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            // The thread copy also has to forget bugs taken back
+            let mut played = 0;
+            while play_random_moves(&mut game, &mut rng, 1) == 1 && played < 40 {
+                played += 1;
+                let moves = game.valid_moves();
+                assert_eq!(sorted(moves), sorted(game.compute_valid_moves()));
+            }
+            for _ in 0..played {
+                game.undo_move();
+                let moves = game.valid_moves();
+                assert_eq!(sorted(moves), sorted(game.compute_valid_moves()));
+            }
         }
     }
+
+    #[test]
+    fn pinned_tiles_match_connectivity() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            while play_random_moves(&mut game, &mut rng, 1) == 1 && game.n_moves() < 60 {
+                let hive = game.get_hive().as_ref().unwrap();
+                let pinned = hive.pinned_tiles();
+                for &tile in hive.tiles() {
+                    let mut without = hive.clone();
+                    without.remove_bug(hive.top_bug(tile).unwrap());
+                    let splits = !without.is_connected();
+                    assert_eq!(pinned.contains(&tile), splits, "seed {seed} {tile}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn moves_written_from_any_neighbor_are_accepted() {
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut game = Game::from_game_string("Base+MLP").unwrap();
+            play_random_moves(&mut game, &mut rng, 20 + seed as usize);
+            let hive = game.get_hive().clone().unwrap();
+            for m in game.compute_valid_moves() {
+                assert!(m.source.is_none() || m.source != m.target, "{m}");
+                let Some(destination) = hive.move_destination(&m) else {
+                    continue;
+                };
+                for (neighbor, direction) in hive.get_nearby_bugs(destination) {
+                    if Some(neighbor) == m.source {
+                        continue;
+                    }
+                    let other = Move::new(m.source.unwrap(), Some(neighbor), Some(direction));
+                    assert!(game.normalize_move(other) == Some(m), "{other} for {m}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn play_normalizes_move_string() {
+        // North west of wS1 is also north east of wQ
+        let mut game = Game::from_game_string(QUEENS_OUT).unwrap();
+        let mut other = game.clone();
+        game.try_play_move(Move::from_str("wA1 \\wS1").unwrap())
+            .unwrap();
+        other
+            .try_play_move(Move::from_str("wA1 wQ/").unwrap())
+            .unwrap();
+        assert_eq!(game.moves_string(), other.moves_string());
+        assert!(game == other);
+
+        // South east of wS1 touches bS1
+        let mut game = Game::from_game_string(QUEENS_OUT).unwrap();
+        assert_eq!(
+            game.try_play_move(Move::from_str("wA1 wS1\\").unwrap()),
+            Err(HiveError::InvalidMove("wA1 wS1\\".to_string()))
+        );
+
+        // Only the first move is written without a target
+        let mut game = Game::from_game_string(
+            "Base;InProgress;White[4];wS1;bS1 wS1-;wQ -wS1;bQ bS1-;wB1 wQ/;bA1 bQ-",
+        )
+        .unwrap();
+        assert_eq!(
+            game.try_play_move(Move::from_str("wB1").unwrap()),
+            Err(HiveError::InvalidMove("wB1".to_string()))
+        );
+        let mut game = Game::from_game_string("Base").unwrap();
+        game.try_play_move(Move::from_str("wS1").unwrap()).unwrap();
+        assert_eq!(game.moves_string(), "wS1");
+    }
 }
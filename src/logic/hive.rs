@@ -1,43 +1,126 @@
-use crate::logic::bugs::bug::{Bug, Color};
+use crate::logic::bugs::bug::{Bug, BugKind, Color, N_BUGS};
 use crate::logic::r#move::Move;
 use crate::logic::tile::{Direction, Tile, REVERSE_DIRECTION};
+use crate::logic::zobrist;
 use log::debug;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::ops::Not;
 
+// Side of the wrapped grid, a hive of N_BUGS bugs can't span more tiles than
+// that in any direction so tiles around it never share a cell
+const GRID_SIZE: usize = 32;
+// Ground bug, four beetles and two mosquitoes
+const MAX_HEIGHT: usize = 7;
+
+#[derive(Clone, Copy)]
+struct Stack {
+    // Only the first height bugs are meaningful
+    bugs: [Bug; MAX_HEIGHT],
+    height: usize,
+}
+
+const EMPTY_STACK: Stack = Stack {
+    bugs: [Bug {
+        kind: BugKind::Queen,
+        index: 0,
+        color: Color::White,
+    }; MAX_HEIGHT],
+    height: 0,
+};
+
+impl Stack {
+    fn bugs(&self) -> &[Bug] {
+        &self.bugs[..self.height]
+    }
+}
+
+impl PartialEq for Stack {
+    fn eq(&self, other: &Self) -> bool {
+        self.bugs() == other.bugs()
+    }
+}
+
+fn cell_index(tile: Tile) -> usize {
+    // Two's complement keeps the wrapping consistent for negative coordinates
+    let q = tile.q as u8 as usize % GRID_SIZE;
+    let r = tile.r as u8 as usize % GRID_SIZE;
+    q * GRID_SIZE + r
+}
+
 #[derive(Clone)]
 pub struct Hive {
-    bugs: HashMap<Tile, Vec<Bug>>,
+    cells: Vec<Stack>,
+    // Tile of every bug in the hive, indexed by Bug::slot
+    positions: [Option<Tile>; N_BUGS],
+    // Occupied tiles, in no particular order
+    tiles: Vec<Tile>,
+    // Zobrist key of the bugs positions, kept up to date on every change
+    key: u64,
+}
+
+impl PartialEq for Hive {
+    fn eq(&self, other: &Self) -> bool {
+        self.cells == other.cells
+    }
+}
+
+impl Default for Hive {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Hive {
     pub fn new() -> Self {
         Hive {
-            bugs: HashMap::new(),
+            cells: vec![EMPTY_STACK; GRID_SIZE * GRID_SIZE],
+            positions: [None; N_BUGS],
+            tiles: vec![],
+            key: 0,
         }
     }
 
     pub fn get_n_tiles(&self) -> usize {
-        self.bugs.len()
+        self.tiles.len()
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
     }
 
-    pub fn get_bugs(&self) -> &HashMap<Tile, Vec<Bug>> {
-        &self.bugs
+    // Stacks of the occupied tiles, built on request as the hive is kept on a grid
+    pub fn get_bugs(&self) -> HashMap<Tile, Vec<Bug>> {
+        self.tiles
+            .iter()
+            .map(|&tile| (tile, self.stack(tile).to_vec()))
+            .collect()
+    }
+
+    pub fn is_occupied(&self, tile: Tile) -> bool {
+        self.cells[cell_index(tile)].height > 0
+    }
+
+    // Bugs on tile from the ground up, empty if the tile is free
+    pub fn stack(&self, tile: Tile) -> &[Bug] {
+        self.cells[cell_index(tile)].bugs()
+    }
+
+    pub fn stack_height(&self, tile: Tile) -> usize {
+        self.cells[cell_index(tile)].height
+    }
+
+    pub fn top_bug(&self, tile: Tile) -> Option<Bug> {
+        self.stack(tile).last().copied()
     }
 
     pub fn get_nearby_bugs(&self, tile: Tile) -> Vec<(Bug, Direction)> {
         let mut bugs_directions: Vec<(Bug, Direction)> = vec![];
         let tile_neighbors = tile.neighbors();
         for (t, direction) in tile_neighbors.iter().zip(REVERSE_DIRECTION) {
-            match self.bugs.get(&t) {
-                Some(bugs_on_tile) => {
-                    for bug in bugs_on_tile {
-                        bugs_directions.push((*bug, direction))
-                    }
-                }
-                None => {}
-            };
+            for bug in self.stack(*t) {
+                bugs_directions.push((*bug, direction))
+            }
         }
         bugs_directions
     }
@@ -46,7 +129,7 @@ impl Hive {
         let mut n_surround = 0;
         let tile_neighbors = tile.neighbors();
         for t in tile_neighbors {
-            if self.bugs.get(&t).is_some() {
+            if self.is_occupied(t) {
                 n_surround += 1
             }
         }
@@ -55,66 +138,96 @@ impl Hive {
 
     // Play a given move
     pub fn play_move(&mut self, m: Move) {
+        let Some(source) = m.source else {
+            // Passing leaves the hive untouched
+            return;
+        };
         if m.is_first_piece() {
             let tile = Tile::new(0, 0, 0);
-            self.add_bug(tile, m.source);
+            self.add_bug(tile, source);
         } else {
             let target = m.target.expect("Couldn't find target.");
-            self.place_bug_relative(m.source, target, m.direction)
+            self.place_bug_relative(source, target, m.direction)
         }
     }
 
     // Add a bug to the hive at specified tile
     pub fn add_bug(&mut self, tile: Tile, bug: Bug) {
-        let bugs = self.bugs.get_mut(&tile);
-        if let Some(vec) = bugs {
+        let slot = bug.slot().expect("Bug is not part of the piece set.");
+        let stack = &mut self.cells[cell_index(tile)];
+        if stack.height > 0 {
             debug!("stacking {bug} on tile {tile}");
-            vec.push(bug);
         } else {
             debug!("placing {bug} on tile {tile}");
-            self.bugs.insert(tile, vec![bug]);
+            self.tiles.push(tile);
         }
+        self.key ^= zobrist::bug_key(bug, tile, stack.height);
+        stack.bugs[stack.height] = bug;
+        stack.height += 1;
+        self.positions[slot] = Some(tile);
     }
 
     // Removes a bug from the hive
     pub fn remove_bug(&mut self, bug: Bug) {
         let tile = self.find_bug(&bug).expect("Couldn't find bug.");
-        let bugs = self.bugs.get_mut(&tile);
         debug!("removing {bug} from tile {tile}");
-        if let Some(vec) = bugs {
-            if vec.len() > 1 {
-                vec.retain(|&x| x != bug);
-            } else {
-                self.bugs.remove(&tile);
-            }
+        let stack = &mut self.cells[cell_index(tile)];
+        // Bugs above the removed one go down a level
+        let height = stack.bugs().iter().position(|&x| x == bug).unwrap();
+        for h in height..stack.height {
+            self.key ^= zobrist::bug_key(stack.bugs[h], tile, h);
+        }
+        for h in height + 1..stack.height {
+            self.key ^= zobrist::bug_key(stack.bugs[h], tile, h - 1);
+            stack.bugs[h - 1] = stack.bugs[h];
+        }
+        stack.height -= 1;
+        if stack.height == 0 {
+            self.tiles.retain(|&t| t != tile);
+        }
+        self.positions[bug.slot().unwrap()] = None;
+    }
+
+    // Holds the same bugs as other, only the occupied cells of both hives are
+    // written so the grid isn't copied as a whole
+    pub fn copy_from(&mut self, other: &Hive) {
+        for &tile in &self.tiles {
+            self.cells[cell_index(tile)] = EMPTY_STACK;
+        }
+        for &tile in &other.tiles {
+            self.cells[cell_index(tile)] = other.cells[cell_index(tile)];
         }
+        self.positions = other.positions;
+        self.tiles.clone_from(&other.tiles);
+        self.key = other.key;
+    }
+
+    // Looks at the hive with a bug on top of its stack taken out, then puts it back
+    pub fn with_bug_lifted<T>(&mut self, bug: Bug, f: impl FnOnce(&Hive) -> T) -> T {
+        let tile = self.find_bug(&bug).expect("Couldn't find bug.");
+        assert!(self.top_bug(tile) == Some(bug), "Bug is covered.");
+        self.remove_bug(bug);
+        let result = f(self);
+        self.add_bug(tile, bug);
+        result
     }
 
     // Returns the tile a bug is on
     pub fn find_bug(&self, bug: &Bug) -> Option<Tile> {
-        self.bugs.iter().find_map(|(key, &ref val)| {
-            if val.contains(bug) {
-                Some(key.clone())
-            } else {
-                None
-            }
-        })
+        self.positions[bug.slot()?]
     }
 
     pub fn get_bugs_on_tile(&self, tile: Tile) -> Option<Vec<Bug>> {
-        self.bugs.get(&tile).cloned()
+        let bugs = self.stack(tile);
+        (!bugs.is_empty()).then(|| bugs.to_vec())
     }
 
     pub fn count_bugs_of_color(&self, color: Color) -> i32 {
-        let mut score = 0;
-        for (_, bugs) in &self.bugs {
-            for bug in bugs {
-                if bug.color == color {
-                    score += 1;
-                }
-            }
-        }
-        score
+        let offset = if color == Color::White { 0 } else { N_BUGS / 2 };
+        self.positions[offset..offset + N_BUGS / 2]
+            .iter()
+            .filter(|position| position.is_some())
+            .count() as i32
     }
 
     // Place an other bug relative to a bug in a given direction
@@ -125,7 +238,7 @@ impl Hive {
 
         let target_tile = match direction {
             Some(d) => source_tile.move_towards(d, 1),
-            None => source_tile.clone(),
+            None => source_tile,
         };
 
         // If already on the board, delete if from previous tile
@@ -137,36 +250,180 @@ impl Hive {
         self.add_bug(target_tile, other);
     }
 
+    // Zobrist key of the bugs positions
+    pub fn position_hash(&self) -> u64 {
+        self.key
+    }
+
+    // Tile a move lands on, evaluated before playing it
+    pub fn move_destination(&self, m: &Move) -> Option<Tile> {
+        m.source?;
+        let Some(target) = m.target else {
+            // Only the first bug is written without a target
+            return self.tiles.is_empty().then(|| Tile::new(0, 0, 0));
+        };
+        let tile = self.find_bug(&target)?;
+        Some(match m.direction {
+            Some(d) => tile.move_towards(d, 1),
+            None => tile,
+        })
+    }
+
     pub fn is_connected(&self) -> bool {
-        let start = self.bugs.keys().next().expect("Hive has so tile");
-        let mut stack: VecDeque<Tile> = VecDeque::new();
-        let mut visited: HashSet<Tile> = HashSet::new();
-        stack.push_back(*start);
-
-        while stack.len() > 0 {
-            let node = stack.pop_back().expect("Empty stack");
-            visited.insert(node);
-            let neighbors = node.neighbors();
-            let occupied_neighbors = neighbors
-                .iter()
-                .filter(|tile| self.bugs.get(tile).is_some());
-            for neigh in occupied_neighbors {
-                if visited.contains(&neigh).not() {
-                    stack.push_back(*neigh)
+        let Some(start) = self.tiles.first() else {
+            // An empty hive is in one piece
+            return true;
+        };
+        let mut stack: Vec<Tile> = vec![*start];
+        let mut visited = [false; GRID_SIZE * GRID_SIZE];
+        visited[cell_index(*start)] = true;
+        let mut n_visited = 1;
+
+        while let Some(node) = stack.pop() {
+            for neigh in node.neighbors() {
+                let index = cell_index(neigh);
+                if self.is_occupied(neigh) & visited[index].not() {
+                    visited[index] = true;
+                    n_visited += 1;
+                    stack.push(neigh)
+                }
+            }
+        }
+
+        n_visited == self.tiles.len()
+    }
+
+    // Tiles whose bug can't leave without splitting the hive, found as the
+    // articulation points of the occupied tiles in a single traversal
+    pub fn pinned_tiles(&self) -> HashSet<Tile> {
+        let mut search = ArticulationSearch {
+            time: 0,
+            discovery: vec![0; GRID_SIZE * GRID_SIZE],
+            low: vec![0; GRID_SIZE * GRID_SIZE],
+            points: HashSet::new(),
+        };
+        if let Some(&root) = self.tiles.first() {
+            search.visit(self, root, None);
+        }
+        // Leaving a stack uncovers the bug below, the tile stays occupied
+        search
+            .points
+            .into_iter()
+            .filter(|&tile| self.stack_height(tile) == 1)
+            .collect()
+    }
+}
+
+// State of the Tarjan depth first search, indexed by cell
+struct ArticulationSearch {
+    time: usize,
+    // Visit order of each tile, 0 if not visited yet
+    discovery: Vec<usize>,
+    // Earliest visited tile reachable from the subtree through one back edge
+    low: Vec<usize>,
+    points: HashSet<Tile>,
+}
+
+impl ArticulationSearch {
+    fn visit(&mut self, hive: &Hive, tile: Tile, parent: Option<Tile>) {
+        let index = cell_index(tile);
+        self.time += 1;
+        self.discovery[index] = self.time;
+        self.low[index] = self.time;
+        let mut children = 0;
+
+        for neigh in tile.neighbors() {
+            if !hive.is_occupied(neigh) {
+                continue;
+            }
+            let neigh_index = cell_index(neigh);
+            if self.discovery[neigh_index] == 0 {
+                children += 1;
+                self.visit(hive, neigh, Some(tile));
+                self.low[index] = self.low[index].min(self.low[neigh_index]);
+                // The subtree can't get back above this tile without it
+                if parent.is_some() & (self.low[neigh_index] >= self.discovery[index]) {
+                    self.points.insert(tile);
                 }
+            } else if Some(neigh) != parent {
+                self.low[index] = self.low[index].min(self.discovery[neigh_index]);
             }
         }
 
-        visited.len() == self.bugs.len()
+        // The root is a cut point when it links separate subtrees
+        if parent.is_none() & (children > 1) {
+            self.points.insert(tile);
+        }
     }
 }
 
 impl Display for Hive {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let mut description = "".to_string();
-        for (tile, bug) in &self.bugs {
-            description = description + &format!("{tile}: {:?}\n", bug);
+        for tile in &self.tiles {
+            description = description + &format!("{tile}: {:?}\n", self.stack(*tile));
         }
         write!(f, "{description}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::hive::Hive;
+    use crate::logic::tile::{Direction, Tile};
+    use std::collections::HashSet;
+
+    #[test]
+    fn stacks_and_bug_index() {
+        let origin = Tile::new(0, 0, 0);
+        let west = origin.move_towards(Direction::W, 1);
+        let queen = Bug::new(BugKind::Queen, 0, Color::White);
+        let beetle = Bug::new(BugKind::Beetle, 1, Color::Black);
+        let mosquito = Bug::new(BugKind::Mosquito, 0, Color::Black);
+
+        let mut hive = Hive::new();
+        hive.add_bug(origin, queen);
+        hive.add_bug(origin, beetle);
+        hive.add_bug(origin, mosquito);
+        hive.add_bug(west, Bug::new(BugKind::Ant, 3, Color::White));
+        assert_eq!(hive.get_n_tiles(), 2);
+        assert_eq!(hive.stack(origin), &[queen, beetle, mosquito]);
+        assert_eq!(hive.find_bug(&mosquito), Some(origin));
+        assert_eq!(hive.count_bugs_of_color(Color::Black), 2);
+        assert_eq!(hive.get_bugs().len(), 2);
+        assert_eq!(hive.get_bugs()[&origin], vec![queen, beetle, mosquito]);
+
+        // Bugs above a removed bug go down without changing order
+        hive.remove_bug(beetle);
+        assert_eq!(hive.stack(origin), &[queen, mosquito]);
+        assert_eq!(hive.find_bug(&beetle), None);
+        hive.remove_bug(queen);
+        hive.remove_bug(mosquito);
+        assert!(!hive.is_occupied(origin));
+        assert_eq!(hive.tiles(), &[west]);
+
+        // Bugs outside of the piece set are never found
+        assert_eq!(
+            hive.find_bug(&Bug::new(BugKind::Spider, 3, Color::White)),
+            None
+        );
+    }
+
+    #[test]
+    fn covered_tiles_are_not_pinned() {
+        // Line of three tiles, the middle one links the others
+        let origin = Tile::new(0, 0, 0);
+        let west = origin.move_towards(Direction::W, 1);
+        let east = origin.move_towards(Direction::E, 1);
+        let mut hive = Hive::new();
+        hive.add_bug(west, Bug::new(BugKind::Ant, 1, Color::White));
+        hive.add_bug(origin, Bug::new(BugKind::Queen, 0, Color::White));
+        hive.add_bug(east, Bug::new(BugKind::Ant, 1, Color::Black));
+        assert_eq!(hive.pinned_tiles(), HashSet::from([origin]));
+
+        // A beetle on top can leave, the queen below keeps the hive together
+        hive.add_bug(origin, Bug::new(BugKind::Beetle, 1, Color::Black));
+        assert!(hive.pinned_tiles().is_empty());
+    }
+}
@@ -1,7 +1,11 @@
-mod bugs;
-mod eval;
+pub mod alphabeta;
+pub mod bugs;
+pub mod eval;
 pub mod game;
 pub mod hive;
 pub mod r#move;
+pub mod perft;
 pub mod player;
+pub mod search;
 pub mod tile;
+pub mod zobrist;
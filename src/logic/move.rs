@@ -5,7 +5,8 @@ use std::str::FromStr;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Move {
-    pub source: Bug,
+    // None for a pass
+    pub source: Option<Bug>,
     pub target: Option<Bug>,
     pub direction: Option<Direction>,
 }
@@ -13,83 +14,80 @@ pub struct Move {
 impl Move {
     pub fn new(source: Bug, target: Option<Bug>, direction: Option<Direction>) -> Move {
         Move {
-            source,
+            source: Some(source),
             target,
             direction,
         }
     }
 
+    pub fn pass() -> Move {
+        Move {
+            source: None,
+            target: None,
+            direction: None,
+        }
+    }
+
+    pub fn is_pass(&self) -> bool {
+        self.source.is_none()
+    }
+
     pub fn is_first_piece(&self) -> bool {
-        self.target.is_none()
+        self.source.is_some() & self.target.is_none()
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseMoveError;
 
+impl From<ParseBugError> for ParseMoveError {
+    fn from(_: ParseBugError) -> Self {
+        ParseMoveError
+    }
+}
+
 impl FromStr for Move {
-    type Err = ParseBugError;
+    type Err = ParseMoveError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let source_str;
-        let target_str;
-        let m = if s.contains(" ") {
-            let split: Vec<&str> = s.split(" ").collect();
-            (source_str, target_str) = (split.get(0).unwrap(), split.get(1).unwrap());
-            let source = Bug::from_str(source_str).expect("Couldn't parse source bug.");
-            let target = Bug::from_str(target_str);
-            if target.is_ok() {
-                Move::new(source, Some(target.unwrap()), None)
-            } else {
-                let dir_char_set = ["/", "\\", "-"];
-                let w_move =
-                    dir_char_set.contains(&target_str.chars().nth(0).unwrap().to_string().as_str());
-                let dir_char = if w_move {
-                    target_str.chars().nth(0).unwrap()
-                } else {
-                    target_str.chars().last().unwrap()
-                };
-
-                let direction = match dir_char {
-                    '/' => {
-                        if w_move {
-                            Ok(Direction::SW)
-                        } else {
-                            Ok(Direction::NE)
-                        }
-                    }
-                    '\\' => {
-                        if w_move {
-                            Ok(Direction::NW)
-                        } else {
-                            Ok(Direction::SE)
-                        }
-                    }
-                    '-' => {
-                        if w_move {
-                            Ok(Direction::W)
-                        } else {
-                            Ok(Direction::E)
-                        }
-                    }
-                    _ => Err(ParseMoveError),
-                };
-                let target_str_no_dir = target_str.replace(dir_char, "");
-                let target = Bug::from_str(target_str_no_dir.as_str())
-                    .expect("Couldn't parse target bug in move.");
-                Move::new(source, Some(target), direction.ok())
-            }
-        } else {
-            let source = Bug::from_str(s).expect("Couldn't parse bug in first move.");
-            Move::new(source, None, None)
+        if s == "pass" {
+            return Ok(Move::pass());
+        }
+        let (source_str, target_str) = match s.split_once(' ') {
+            Some((source_str, target_str)) => (source_str, target_str),
+            // First piece of the game
+            None => return Ok(Move::new(Bug::from_str(s)?, None, None)),
         };
-        Ok(m)
+        let source = Bug::from_str(source_str)?;
+
+        // On top of the target bug
+        if let Ok(target) = Bug::from_str(target_str) {
+            return Ok(Move::new(source, Some(target), None));
+        }
+
+        // Direction characters before the target are on its west side
+        let first = target_str.chars().next().ok_or(ParseMoveError)?;
+        let last = target_str.chars().last().ok_or(ParseMoveError)?;
+        let (direction, target_str) = match (first, last) {
+            ('/', _) => (Direction::SW, &target_str[1..]),
+            ('\\', _) => (Direction::NW, &target_str[1..]),
+            ('-', _) => (Direction::W, &target_str[1..]),
+            (_, '/') => (Direction::NE, &target_str[..target_str.len() - 1]),
+            (_, '\\') => (Direction::SE, &target_str[..target_str.len() - 1]),
+            (_, '-') => (Direction::E, &target_str[..target_str.len() - 1]),
+            _ => return Err(ParseMoveError),
+        };
+        let target = Bug::from_str(target_str)?;
+        Ok(Move::new(source, Some(target), Some(direction)))
     }
 }
 
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        let source_str = self.source.to_string();
+        let source_str = match self.source {
+            Some(source) => source.to_string(),
+            None => return write!(f, "pass"),
+        };
         let target = self.target;
         match target {
             Some(target_str) => match self.direction {
@@ -116,7 +114,7 @@ impl Display for Move {
                 }
             },
             None => {
-                let move_str = format!("{source_str}");
+                let move_str = source_str.to_string();
                 write!(f, "{move_str}")
             }
         }
@@ -125,9 +123,10 @@ impl Display for Move {
 
 #[cfg(test)]
 mod tests {
-    use crate::bug::{Bug, BugKind, Color};
-    use crate::r#move::Move;
-    use crate::tile::Direction;
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::r#move::Move;
+    use crate::logic::tile::Direction;
+    use std::str::FromStr;
 
     #[test]
     fn move_to_string() {
@@ -143,4 +142,24 @@ mod tests {
         let m = Move::new(ant, None, Some(Direction::NW));
         assert_eq!(format!("{m}"), "wA1".to_string())
     }
+
+    #[test]
+    fn pass_to_string_and_back() {
+        let m = Move::pass();
+        assert_eq!(format!("{m}"), "pass".to_string());
+        assert!(Move::from_str("pass").unwrap() == m);
+        assert!(m.is_pass() & !m.is_first_piece());
+    }
+
+    #[test]
+    fn malformed_moves_are_errors() {
+        for move_str in [
+            "", " ", "wX1", "wQ ", "wQ -", "wQ bQ/ x", "wQ -bQ/", "wS1 bQ-x",
+        ] {
+            assert!(Move::from_str(move_str).is_err(), "{move_str:?}");
+        }
+        let m = Move::from_str("wS1 -bQ").unwrap();
+        assert!(m.direction == Some(Direction::W));
+        assert_eq!(m.to_string(), "wS1 -bQ");
+    }
 }
@@ -0,0 +1,240 @@
+use crate::logic::game::{Game, GameState};
+use crate::logic::r#move::Move;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+// Number of leaf nodes depth moves ahead, games over have no moves
+pub fn perft(game: &mut Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    if game.state != GameState::InProgress {
+        return 0;
+    }
+    let moves = game.compute_valid_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for m in moves {
+        let undo = game.make_move(m);
+        nodes += perft(game, depth - 1);
+        game.unmake_move(undo);
+    }
+    nodes
+}
+
+// Perft of the subtree of every root move, root moves are shared between threads
+pub fn divide(game: &Game, depth: u32, threads: usize) -> Vec<(Move, u64)> {
+    if (depth == 0) | (game.state != GameState::InProgress) {
+        return vec![];
+    }
+    let moves = game.valid_moves();
+    let next_move = AtomicUsize::new(0);
+    let counts = Mutex::new(vec![0; moves.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut game = game.clone();
+                loop {
+                    let i = next_move.fetch_add(1, Ordering::Relaxed);
+                    let Some(&m) = moves.get(i) else {
+                        break;
+                    };
+                    let undo = game.make_move(m);
+                    let nodes = perft(&mut game, depth - 1);
+                    game.unmake_move(undo);
+                    counts.lock().expect("Couldn't lock perft counts.")[i] = nodes;
+                }
+            });
+        }
+    });
+
+    let counts = counts.into_inner().expect("Couldn't get perft counts.");
+    moves.into_iter().zip(counts).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::game::{Game, GameState};
+    use crate::logic::perft::{divide, perft};
+    use crate::logic::r#move::Move;
+    use std::str::FromStr;
+
+    // Counts from the start position published on the Mzinga wiki, see
+    // https://github.com/jonthysell/Mzinga/wiki/Perft, with the queen not allowed
+    // on the first turn and a single placement per bug type
+    const BASE: [u64; 7] = [1, 4, 96, 1440, 21600, 516240, 12219480];
+
+    // Counts of the first three plies follow from the opening rules: white places
+    // any of its kinds but the queen, black any of its kinds but the queen on the
+    // 6 tiles around it, then white any kind it has left on the 3 tiles touching
+    // only its own bug. Kinds made of a single bug are gone once placed.
+    fn opening_counts(kinds: u64, single_kinds: u64) -> [u64; 4] {
+        let first = kinds - 1;
+        let second = first * (kinds - 1) * 6;
+        let kinds_left = (first - single_kinds) * kinds + single_kinds * (kinds - 1);
+        [1, first, second, kinds_left * (kinds - 1) * 6 * 3]
+    }
+
+    // Game types with their number of bug kinds, queen included, and how many of
+    // them are a single bug, queen excluded
+    const GAME_TYPES: [(&str, u64, u64); 5] = [
+        ("Base", 5, 0),
+        ("Base+M", 6, 1),
+        ("Base+L", 6, 1),
+        ("Base+P", 6, 1),
+        ("Base+MLP", 8, 3),
+    ];
+
+    // Middlegame positions from random games, no reference counts exist for
+    // them so the counts are recorded from this engine and the moves found are
+    // also checked for consistency
+    const MIDDLEGAME: [(&str, [u64; 4]); 5] = [
+        (
+            "Base;InProgress;White[10];wS1;bB1 \\wS1;wQ /wS1;bB2 bB1/;wB1 /wQ;bQ \\bB2;wA1 /wB1;\
+             bA1 bQ-;wS2 /wA1;bA1 -wS2;wA2 wQ-;bA1 bA1\\;wA2 \\bQ;bA1 wQ-;wA2 wS2-;bA2 bQ-;\
+             wA2 wA1-;bG1 -bB1",
+            [1, 52, 4236, 232409],
+        ),
+        (
+            "Base+M;InProgress;White[10];wG1;bA1 wG1/;wS1 -wG1;bQ bA1/;wB1 \\wS1;bS1 bQ-;\
+             wQ wS1\\;bS2 bA1-;wS2 -wB1;bG1 bS2\\;wB2 wS2/;bG1 \\bQ;wM wQ-;bA2 -bQ;wS2 wB2/;\
+             bA2 wQ\\;wS2 -bQ;bA2 -wQ",
+            [1, 27, 1600, 55680],
+        ),
+        (
+            "Base+L;InProgress;White[10];wA1;bS1 \\wA1;wA2 wA1-;bG1 bS1/;wG1 wA2\\;bG2 -bS1;\
+             wQ wG1\\;bQ \\bG1;wA3 wA2-;bQ -bG1;wL wA3/;bL -bQ;wS1 wL/;bG3 \\bL;wB1 wQ-;\
+             bS2 -bL;wS1 bS1-;bS2 bG3/",
+            [1, 77, 2489, 191445],
+        ),
+        (
+            "Base+P;InProgress;White[10];wS1;bG1 /wS1;wQ \\wS1;bB1 /bG1;wS2 wQ/;bS1 -bG1;\
+             wP wS2-;bQ -bS1;wP wQ-;bS2 -bB1;wG1 wS2-;bP bQ/;wA1 wS2/;bA1 bB1-;wB1 wP-;\
+             bA2 bB1\\;wG2 wB1\\;bA3 -bQ",
+            [1, 68, 7219, 468552],
+        ),
+        (
+            "Base+MLP;InProgress;White[10];wL;bP wL-;wS1 -wL;bG1 bP\\;wM wS1/;bL /bG1;wQ /wS1;\
+             bQ bP/;wM bP-;bL bG1-;wA1 -wS1;bL -bQ;wA1 wM-;bL bL/;wA1 bL/;bG1 -bQ;wB1 wA1/;\
+             bG1 wQ-",
+            [1, 69, 1038, 74341],
+        ),
+    ];
+
+    fn check(game_string: &str, counts: &[u64], depths: impl Iterator<Item = usize>) {
+        let mut game = Game::from_game_string(game_string).unwrap();
+        for depth in depths {
+            let nodes = perft(&mut game, depth as u32);
+            assert_eq!(nodes, counts[depth], "{game_string} at depth {depth}");
+        }
+    }
+
+    // Perft playing every move on its own copy of the game from its move string,
+    // the way moves come from a GUI, moves of the last ply are only read back
+    fn perft_from_strings(game: &Game, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        if game.state != GameState::InProgress {
+            return 0;
+        }
+        let moves = game.valid_moves();
+        if depth == 1 {
+            for m in &moves {
+                assert!(Move::from_str(&m.to_string()).ok() == Some(*m), "{m}");
+            }
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for m in moves {
+            let mut child = game.clone();
+            child
+                .try_play_move(Move::from_str(&m.to_string()).unwrap())
+                .unwrap();
+            nodes += perft_from_strings(&child, depth - 1);
+        }
+        nodes
+    }
+
+    #[test]
+    fn base_start_position() {
+        check("Base", &BASE, 0..=4);
+    }
+
+    #[test]
+    #[ignore]
+    fn base_start_position_deep() {
+        check("Base", &BASE, 5..=6);
+    }
+
+    #[test]
+    fn start_positions_follow_opening_rules() {
+        assert_eq!(opening_counts(5, 0), BASE[..4]);
+        for (game_type, kinds, single_kinds) in GAME_TYPES {
+            check(game_type, &opening_counts(kinds, single_kinds), 0..=3);
+        }
+    }
+
+    #[test]
+    fn middlegame_positions() {
+        for (game_string, counts) in MIDDLEGAME {
+            check(game_string, &counts, 0..=2);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn middlegame_positions_deep() {
+        for (game_string, counts) in MIDDLEGAME {
+            check(game_string, &counts, 3..=3);
+        }
+    }
+
+    #[test]
+    fn middlegame_moves_are_distinct() {
+        for (game_string, _) in MIDDLEGAME {
+            let mut game = Game::from_game_string(game_string).unwrap();
+            for m in game.compute_valid_moves() {
+                let undo = game.make_move(m);
+                let moves = game.compute_valid_moves();
+                let hive = game.get_hive().as_ref().unwrap();
+                let destinations: Vec<_> = moves
+                    .iter()
+                    .map(|m| (m.source, hive.move_destination(m)))
+                    .collect();
+                for (i, destination) in destinations.iter().enumerate() {
+                    assert!(
+                        !destinations[..i].contains(destination),
+                        "{game_string} after {m}: {}",
+                        moves[i]
+                    );
+                }
+                game.unmake_move(undo);
+            }
+        }
+    }
+
+    #[test]
+    fn middlegame_perft_matches_played_copies() {
+        for (game_string, _) in MIDDLEGAME {
+            let mut game = Game::from_game_string(game_string).unwrap();
+            let nodes = perft(&mut game, 2);
+            assert_eq!(nodes, perft_from_strings(&game, 2), "{game_string}");
+            assert!(game == Game::from_game_string(game_string).unwrap());
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let mut game = Game::from_game_string("Base+MLP").unwrap();
+        let divided = divide(&game, 3, 4);
+        assert_eq!(divided.len() as u64, perft(&mut game, 1));
+        let total: u64 = divided.iter().map(|(_, nodes)| nodes).sum();
+        assert_eq!(total, perft(&mut game, 3));
+        assert!(game == Game::from_game_string("Base+MLP").unwrap());
+    }
+}
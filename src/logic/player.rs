@@ -1,13 +1,14 @@
 use crate::logic::bugs;
 use crate::logic::bugs::bug::{Bug, BugKind, Color};
+use crate::logic::game::GameType;
 use crate::logic::hive::Hive;
 use crate::logic::r#move::Move;
 use crate::logic::tile::{Direction, Tile};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ops::Not;
 use std::str::FromStr;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Player {
     inactive_pieces: Vec<Bug>,
     active_pieces: Vec<Bug>,
@@ -17,11 +18,25 @@ const PIECE_SET: [&str; 11] = [
     "Q", "S1", "S2", "B1", "B2", "G1", "G2", "G3", "A1", "A2", "A3",
 ];
 
+const MOSQUITO: &str = "M";
+const LADYBUG: &str = "L";
+const PILLBUG: &str = "P";
+
 impl Player {
-    pub fn new(color: Color) -> Self {
+    pub fn new(color: Color, gtype: &GameType) -> Self {
         let mut inactive_pieces = vec![];
         let color_str = if color == Color::White { 'w' } else { 'b' };
-        for piece in PIECE_SET {
+        let mut pieces = PIECE_SET.to_vec();
+        if gtype.has_mosquito() {
+            pieces.push(MOSQUITO);
+        }
+        if gtype.has_ladybug() {
+            pieces.push(LADYBUG);
+        }
+        if gtype.has_pillbug() {
+            pieces.push(PILLBUG);
+        }
+        for piece in pieces {
             let bug = Bug::from_str(format!("{color_str}{piece}").as_str())
                 .expect("Couldn't create bug from string.");
             inactive_pieces.push(bug)
@@ -37,6 +52,19 @@ impl Player {
         self.active_pieces.push(bug);
     }
 
+    // Put a bug back in hand, keeping the piece set order
+    pub fn set_piece_inactive(&mut self, bug: Bug) {
+        self.active_pieces.retain(|&x| x != bug);
+        // Slots follow the piece set order
+        let slot = bug.slot();
+        let position = self
+            .inactive_pieces
+            .iter()
+            .position(|x| x.slot() > slot)
+            .unwrap_or(self.inactive_pieces.len());
+        self.inactive_pieces.insert(position, bug);
+    }
+
     pub fn is_piece_inactive(&self, bug: Bug) -> bool {
         self.inactive_pieces.contains(&bug)
     }
@@ -49,19 +77,17 @@ impl Player {
         // Manage beetle
         let mut c = vec![];
         for tile in tiles {
-            if let Some(t) = hive.get_bugs().get(&tile) {
-                c.push((
-                    Some(t.last().expect("Couldn't get last bug of tile").clone()),
-                    None,
-                ));
+            if let Some(top) = hive.top_bug(tile) {
+                c.push((Some(top), None));
             } else {
-                let nearby = hive.get_nearby_bugs(tile);
-                // Duplicate moves
-                // for (bug, dir) in nearby {
-                //     c.push((Some(bug.clone()), Some(dir.clone())));
-                // }
-                let (b, d) = nearby.first().unwrap();
-                c.push((Some(b.clone()), Some(d.clone())));
+                // Any neighbor would do, the first bug on top of a stack is
+                // the canonical reference
+                let (b, d) = hive
+                    .get_nearby_bugs(tile)
+                    .into_iter()
+                    .find(|(b, _)| hive.find_bug(b).and_then(|t| hive.top_bug(t)) == Some(*b))
+                    .expect("Couldn't find a bug next to the tile.");
+                c.push((Some(b), Some(d)));
             }
         }
         c
@@ -83,9 +109,8 @@ impl Player {
         for tile in tiles.iter() {
             let mut neigh_colors = vec![];
             for neigh_tile in tile.neighbors() {
-                if let Some(bugs) = hive.get_bugs_on_tile(neigh_tile) {
-                    let tile_color = bugs.last().expect("Couldn't get last bug of tile").color;
-                    neigh_colors.push(tile_color);
+                if let Some(top) = hive.top_bug(neigh_tile) {
+                    neigh_colors.push(top.color);
                 }
             }
             if neigh_colors
@@ -132,12 +157,10 @@ impl Player {
         let mut added_grasshopper = 0;
         let mut added_ant = 0;
 
-        let active_bugs = hive.get_bugs().keys();
-        let tiles_with_bugs: HashSet<&Tile> = active_bugs.collect::<HashSet<&Tile>>();
         let mut neighbors_tiles_of_bugs: HashSet<Tile> = Default::default();
-        for tile in tiles_with_bugs {
+        for tile in hive.tiles() {
             for neigh in tile.neighbors() {
-                if hive.get_bugs_on_tile(neigh).is_none() {
+                if !hive.is_occupied(neigh) {
                     neighbors_tiles_of_bugs.insert(neigh);
                 }
             }
@@ -159,8 +182,7 @@ impl Player {
         }
 
         for piece in &check_pieces {
-            let candidates: Vec<(Option<Bug>, Option<Direction>)>;
-            candidates = match hive.get_n_tiles() {
+            let candidates: Vec<(Option<Bug>, Option<Direction>)> = match hive.get_n_tiles() {
                 0 => {
                     // Place on tile (0, 0, 0)
                     vec![(None, None)]
@@ -225,53 +247,107 @@ impl Player {
         moves
     }
 
-    pub fn movement(&self, hive: &Hive) -> Vec<Move> {
+    pub fn movement(&self, hive: &mut Hive, last_moved: Option<Bug>) -> Vec<Move> {
         let mut moves = vec![];
+        // Tiles each bug can be thrown to, by any pillbug
+        let mut thrown: HashMap<Bug, HashSet<Tile>> = HashMap::new();
 
-        let active_bugs = hive.get_bugs();
-        let mut hive_without_current_bug: Hive = hive.clone();
+        let pinned_tiles = hive.pinned_tiles();
 
         for bug in &self.active_pieces {
+            // The bug moved last turn is frozen
+            if Some(*bug) == last_moved {
+                continue;
+            }
             let tile = hive
-                .find_bug(&bug)
+                .find_bug(bug)
                 .expect("Couldn't find tile of active bug.");
-            hive_without_current_bug.remove_bug(*bug);
+            let is_under_other_bug = hive.top_bug(tile) != Some(*bug);
 
-            let bug_z_index = hive
-                .get_bugs_on_tile(tile)
-                .unwrap()
-                .iter()
-                .position(|&b| b == *bug)
-                .unwrap();
-            let is_under_other_bug = (bug_z_index + 1) < hive.get_bugs_on_tile(tile).unwrap().len();
-
-            if hive_without_current_bug.is_connected() & !is_under_other_bug {
+            // Bugs which need the hive without them are lifted and put back, only a
+            // bug on top of its stack can be put back without reordering the stack
+            if !is_under_other_bug & !pinned_tiles.contains(&tile) {
                 let candidate_tiles = match bug.kind {
-                    BugKind::Queen => bugs::queen::moves(tile, active_bugs),
-                    BugKind::Beetle => bugs::beetle::moves(tile, &hive_without_current_bug),
+                    BugKind::Queen => bugs::queen::moves(tile, hive),
+                    BugKind::Beetle => {
+                        hive.with_bug_lifted(*bug, |hive| bugs::beetle::moves(tile, hive))
+                    }
                     BugKind::Grasshopper => {
-                        bugs::grasshopper::moves(tile, &hive_without_current_bug)
+                        hive.with_bug_lifted(*bug, |hive| bugs::grasshopper::moves(tile, hive))
                     }
                     BugKind::Spider => {
-                        bugs::spider::moves(tile, hive_without_current_bug.get_bugs())
+                        hive.with_bug_lifted(*bug, |hive| bugs::spider::moves(tile, hive))
                     }
-                    BugKind::Ant => bugs::ant::moves(tile, active_bugs),
+                    BugKind::Ant => bugs::ant::moves(tile, hive),
+                    BugKind::Ladybug => {
+                        hive.with_bug_lifted(*bug, |hive| bugs::ladybug::moves(tile, hive))
+                    }
+                    BugKind::Mosquito => bugs::mosquito::moves(tile, hive),
+                    BugKind::Pillbug => bugs::pillbug::moves(tile, hive),
                 };
-                let bug_dir = self.find_bugs_dir_from_tiles(hive, candidate_tiles);
+                // The moving bug can't be its own reference
+                let bug_dir = hive.with_bug_lifted(*bug, |hive| {
+                    self.find_bugs_dir_from_tiles(hive, candidate_tiles)
+                });
                 let mut current_moves: Vec<Move> = bug_dir
                     .iter()
                     .cloned()
-                    //.filter(|(other, _)| *bug != other.unwrap())
                     .map(|(other, dir)| Move::new(*bug, other, dir))
                     .collect();
                 moves.append(&mut current_moves)
             }
-            hive_without_current_bug.add_bug(tile, *bug);
+
+            let has_pillbug_ability = match bug.kind {
+                BugKind::Pillbug => !is_under_other_bug,
+                BugKind::Mosquito => bugs::mosquito::has_pillbug_ability(tile, hive),
+                _ => false,
+            };
+            if has_pillbug_ability {
+                for (thrown_bug, landing_tiles) in bugs::pillbug::throws(tile, hive, last_moved) {
+                    thrown.entry(thrown_bug).or_default().extend(landing_tiles);
+                }
+            }
+        }
+
+        if !thrown.is_empty() {
+            // A thrown bug may also reach the same tile on its own
+            for m in &moves {
+                if let (Some(bug), Some(tile)) = (m.source, hive.move_destination(m)) {
+                    if let Some(tiles) = thrown.get_mut(&bug) {
+                        tiles.remove(&tile);
+                    }
+                }
+            }
+            moves.append(&mut self.pillbug_throws(hive, thrown));
         }
         moves
     }
 
-    pub fn valid_moves(&self, hive: &Hive, turn_number: u32, turn_color: Color) -> Vec<Move> {
+    // Moves of the bugs thrown by a pillbug
+    fn pillbug_throws(&self, hive: &mut Hive, thrown: HashMap<Bug, HashSet<Tile>>) -> Vec<Move> {
+        let mut moves = vec![];
+        for (thrown_bug, landing_tiles) in thrown {
+            // Thrown bugs are alone on their tile
+            let bug_dir = hive.with_bug_lifted(thrown_bug, |hive| {
+                self.find_bugs_dir_from_tiles(hive, landing_tiles)
+            });
+            moves.extend(
+                bug_dir
+                    .into_iter()
+                    .map(|(other, dir)| Move::new(thrown_bug, other, dir)),
+            );
+        }
+        moves
+    }
+
+    // The hive is changed while looking for moves but left as it was
+    pub fn valid_moves(
+        &self,
+        hive: &mut Hive,
+        turn_number: u32,
+        turn_color: Color,
+        last_moved: Option<Bug>,
+    ) -> Vec<Move> {
         let mut moves = vec![];
 
         // Placing
@@ -285,8 +361,7 @@ impl Player {
 
         // Movement
         if self.is_queen_played(turn_color) {
-            let mut motion_move = self.movement(hive);
-            moves.append(&mut motion_move);
+            moves.append(&mut self.movement(hive, last_moved));
         }
 
         moves
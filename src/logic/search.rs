@@ -0,0 +1,102 @@
+use crate::logic::alphabeta::AlphaBeta;
+use crate::logic::eval::{Eval, EvalKind};
+use crate::logic::game::Game;
+use crate::logic::r#move::Move;
+use minimax::{
+    IterativeOptions, IterativeSearch, Negamax, ParallelOptions, ParallelSearch, Strategy,
+};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SearchAlgorithm {
+    // Engine's own alpha-beta search
+    AlphaBeta,
+    Negamax,
+    Iterative,
+    Parallel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SearchSettings {
+    pub algorithm: SearchAlgorithm,
+    pub evaluation: EvalKind,
+    pub max_depth: u8,
+    // Takes precedence over max_depth for iterative searches
+    pub max_time: Option<Duration>,
+    // Stops the AlphaBeta search once reached without changing max_depth
+    pub time_cap: Option<Duration>,
+    pub threads: usize,
+    pub hash_size_mb: usize,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        SearchSettings {
+            algorithm: SearchAlgorithm::AlphaBeta,
+            evaluation: EvalKind::PieceCount,
+            max_depth: 3,
+            max_time: None,
+            time_cap: None,
+            threads: 1,
+            hash_size_mb: 64,
+        }
+    }
+}
+
+pub fn best_move(game: &Game, settings: &SearchSettings) -> Option<Move> {
+    if settings.algorithm == SearchAlgorithm::AlphaBeta {
+        return AlphaBeta::new(settings).search(&mut game.clone());
+    }
+    let eval = Eval {
+        kind: settings.evaluation,
+    };
+    let options = IterativeOptions::new().with_table_byte_size(settings.hash_size_mb << 20);
+    let mut strategy: Box<dyn Strategy<Game>> = match settings.algorithm {
+        SearchAlgorithm::AlphaBeta | SearchAlgorithm::Negamax => {
+            Box::new(Negamax::new(eval, settings.max_depth))
+        }
+        SearchAlgorithm::Iterative => Box::new(IterativeSearch::new(eval, options)),
+        SearchAlgorithm::Parallel => Box::new(ParallelSearch::new(
+            eval,
+            options,
+            ParallelOptions::new().with_num_threads(settings.threads),
+        )),
+    };
+    match settings.max_time {
+        Some(max_time) => strategy.set_timeout(max_time),
+        None => strategy.set_max_depth(settings.max_depth),
+    }
+    strategy.choose_move(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::eval::EvalKind;
+    use crate::logic::game::Game;
+    use crate::logic::search::{best_move, SearchAlgorithm, SearchSettings};
+
+    #[test]
+    fn every_algorithm_returns_a_valid_move() {
+        let mut game =
+            Game::from_game_string("Base;InProgress;White[3];wS1;bG1 -wS1;wA1 wS1/;bG2 /bG1")
+                .unwrap();
+        for algorithm in [
+            SearchAlgorithm::AlphaBeta,
+            SearchAlgorithm::Negamax,
+            SearchAlgorithm::Iterative,
+            SearchAlgorithm::Parallel,
+        ] {
+            for evaluation in [EvalKind::PieceCount, EvalKind::QueenPressure] {
+                let settings = SearchSettings {
+                    algorithm,
+                    evaluation,
+                    max_depth: 2,
+                    threads: 2,
+                    ..SearchSettings::default()
+                };
+                let m = best_move(&game, &settings).unwrap();
+                assert!(game.compute_valid_moves().contains(&m), "{algorithm:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::logic::bugs::bug::{Bug, BugKind, Color};
+use crate::logic::hive::Hive;
+use crate::logic::tile::Tile;
+
+// Xored into the key when black is to move
+pub const BLACK_TO_MOVE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Tiles are unbounded so keys are derived by mixing the features instead of
+// being read from a table
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn bug_id(bug: Bug) -> u64 {
+    let kind = match bug.kind {
+        BugKind::Queen => 0,
+        BugKind::Beetle => 1,
+        BugKind::Ant => 2,
+        BugKind::Spider => 3,
+        BugKind::Grasshopper => 4,
+        BugKind::Mosquito => 5,
+        BugKind::Ladybug => 6,
+        BugKind::Pillbug => 7,
+    };
+    let color = if bug.color == Color::White { 0 } else { 1 };
+    (color << 8) | (kind << 4) | bug.index as u64
+}
+
+// Key of a bug standing on tile at the given height in the stack, 0 being the ground
+pub fn bug_key(bug: Bug, tile: Tile, height: usize) -> u64 {
+    let q = tile.q as u8 as u64;
+    let r = tile.r as u8 as u64;
+    mix((bug_id(bug) << 32) | (q << 24) | (r << 16) | height as u64)
+}
+
+// Key of the whole hive computed from scratch, the hive keeps its own up to date
+pub fn hive_key(hive: &Hive) -> u64 {
+    let mut key = 0;
+    for tile in hive.tiles() {
+        for (height, bug) in hive.stack(*tile).iter().enumerate() {
+            key ^= bug_key(*bug, *tile, height);
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logic::bugs::bug::{Bug, BugKind, Color};
+    use crate::logic::hive::Hive;
+    use crate::logic::tile::{Direction, Tile};
+    use crate::logic::zobrist::hive_key;
+
+    #[test]
+    fn key_depends_on_position_only() {
+        let origin = Tile::new(0, 0, 0);
+        let east = origin.move_towards(Direction::E, 1);
+        let queen = Bug::new(BugKind::Queen, 0, Color::White);
+        let beetle = Bug::new(BugKind::Beetle, 1, Color::Black);
+
+        let mut hive = Hive::new();
+        hive.add_bug(origin, queen);
+        hive.add_bug(east, beetle);
+        let mut other = Hive::new();
+        other.add_bug(east, beetle);
+        other.add_bug(origin, queen);
+        assert_eq!(hive_key(&hive), hive_key(&other));
+
+        // Same bugs on the same tile, stacked the other way round
+        let mut stacked = Hive::new();
+        stacked.add_bug(origin, queen);
+        stacked.add_bug(origin, beetle);
+        let mut reversed = Hive::new();
+        reversed.add_bug(origin, beetle);
+        reversed.add_bug(origin, queen);
+        assert_ne!(hive_key(&stacked), hive_key(&reversed));
+        assert_ne!(hive_key(&hive), hive_key(&stacked));
+    }
+}
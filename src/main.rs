@@ -1,38 +1,20 @@
-mod engine;
-mod logic;
-
-use crate::engine::Engine;
+use hive_rust::engine::Engine;
 use std::io;
 
 fn main() {
     let mut engine = Engine::new();
-
-    let response = engine.process_command("info\n".to_string());
-
-    match response {
-        Ok(r) => {
-            println!("{r}");
-        }
-        Err(e) => {
-            println!("err {e}")
-        }
-    }
-    println!("ok");
+    print!("{}", engine.uhp_response("info".to_string()));
 
     loop {
         let mut command = String::new();
-        io::stdin()
-            .read_line(&mut command)
-            .expect("Failed to read command.");
-        let response = engine.process_command(command);
-        match response {
-            Ok(r) => {
-                println!("{r}");
-            }
+        match io::stdin().read_line(&mut command) {
+            // Input closed, nothing left to answer
+            Ok(0) => break,
+            Ok(_) => print!("{}", engine.uhp_response(command)),
             Err(e) => {
-                println!("err {e}")
+                eprintln!("Failed to read command: {e}");
+                break;
             }
         }
-        println!("ok")
     }
 }
@@ -0,0 +1,420 @@
+use hive_rust::logic::game::{DrawRules, Game, GameState};
+use hive_rust::logic::r#move::Move;
+use hive_rust::stats::{MatchResult, Sprt, SprtResult};
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Child, ChildStdin, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: match --engine1 CMD --engine2 CMD [--option1 Name=Value]... \
+[--option2 Name=Value]... [--games N] [--tc SECONDS+INCREMENT | --depth N] [--game-type GameType] \
+[--max-turns N] [--random-plies N] [--sprt ELO0,ELO1]";
+// Time allowed to answer commands which are not searches
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+// Searches to a fixed depth are still stopped at some point
+const DEPTH_TIMEOUT: Duration = Duration::from_secs(3600);
+// Moves left in the game assumed when splitting the clock
+const MOVES_TO_GO: u32 = 20;
+
+enum Limit {
+    // Seconds on the clock at the start and added after each move
+    Clock { base: f64, increment: f64 },
+    Depth(u8),
+}
+
+struct Args {
+    engines: [String; 2],
+    options: [Vec<(String, String)>; 2],
+    games: u32,
+    limit: Limit,
+    game_type: String,
+    max_turns: u32,
+    random_plies: u32,
+    sprt: Option<Sprt>,
+}
+
+fn parse_option(option: &str) -> Result<(String, String), String> {
+    option
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or(format!("invalid option {option}, expected Name=Value"))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut engines = [None, None];
+    let mut args = Args {
+        engines: Default::default(),
+        options: Default::default(),
+        games: 10,
+        limit: Limit::Depth(2),
+        game_type: "Base".to_string(),
+        max_turns: 200,
+        random_plies: 4,
+        sprt: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("missing value for {arg}"));
+        match arg.as_str() {
+            "--engine1" => engines[0] = Some(value()?),
+            "--engine2" => engines[1] = Some(value()?),
+            "--option1" => args.options[0].push(parse_option(&value()?)?),
+            "--option2" => args.options[1].push(parse_option(&value()?)?),
+            "--games" => {
+                let games = value()?;
+                args.games = games
+                    .parse()
+                    .ok()
+                    .filter(|&g| g > 0)
+                    .ok_or(format!("invalid number of games {games}"))?;
+            }
+            "--tc" => {
+                let tc = value()?;
+                let (base, increment) = tc.split_once('+').unwrap_or((&tc, "0"));
+                args.limit = match (base.parse::<f64>(), increment.parse::<f64>()) {
+                    // UHP searches last whole seconds, the first move must get at least one
+                    (Ok(base), Ok(increment))
+                        if (increment >= 0.0) & (base / MOVES_TO_GO as f64 + increment >= 1.0) =>
+                    {
+                        Limit::Clock { base, increment }
+                    }
+                    _ => return Err(format!("invalid time control {tc}")),
+                };
+            }
+            "--depth" => {
+                let depth = value()?;
+                args.limit = Limit::Depth(
+                    depth
+                        .parse()
+                        .ok()
+                        .filter(|&d| d > 0)
+                        .ok_or(format!("invalid depth {depth}"))?,
+                );
+            }
+            "--game-type" => args.game_type = value()?,
+            "--max-turns" => {
+                let max_turns = value()?;
+                args.max_turns = max_turns
+                    .parse()
+                    .map_err(|_| format!("invalid number of turns {max_turns}"))?;
+            }
+            "--random-plies" => {
+                let plies = value()?;
+                args.random_plies = plies
+                    .parse()
+                    .map_err(|_| format!("invalid number of plies {plies}"))?;
+            }
+            "--sprt" => {
+                let sprt = value()?;
+                args.sprt = match sprt
+                    .split_once(',')
+                    .map(|(e0, e1)| (e0.parse(), e1.parse()))
+                {
+                    Some((Ok(elo0), Ok(elo1))) if elo0 < elo1 => Some(Sprt {
+                        elo0,
+                        elo1,
+                        ..Sprt::default()
+                    }),
+                    _ => return Err(format!("invalid SPRT bounds {sprt}")),
+                };
+            }
+            arg => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    match engines {
+        [Some(engine1), Some(engine2)] => args.engines = [engine1, engine2],
+        _ => return Err("both engines are needed".to_string()),
+    }
+    Ok(args)
+}
+
+// UHP engine running as a child process
+struct UhpEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UhpEngine {
+    // The command is split on whitespace, the first word being the program
+    fn start(command: &str, options: &[(String, String)]) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("empty engine command")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("couldn't start {command}: {e}"))?;
+        let stdin = child.stdin.take().ok_or("couldn't get engine input")?;
+        let stdout = child.stdout.take().ok_or("couldn't get engine output")?;
+
+        // Read on a thread so that an engine which stops answering can be timed out
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UhpEngine {
+            name: command.to_string(),
+            child,
+            stdin,
+            lines,
+        };
+        // The engine introduces itself before taking commands
+        let info = engine.read_response(COMMAND_TIMEOUT)?;
+        if let Some(id) = info.iter().find_map(|line| line.strip_prefix("id ")) {
+            engine.name = id.to_string();
+        }
+        for (name, value) in options {
+            engine.send(&format!("options set {name} {value}"), COMMAND_TIMEOUT)?;
+        }
+        Ok(engine)
+    }
+
+    // Response lines of the last command, without the final ok
+    fn read_response(&mut self, timeout: Duration) -> Result<Vec<String>, String> {
+        let deadline = Instant::now() + timeout;
+        let mut response = vec![];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) if line.trim() == "ok" => return Ok(response),
+                Ok(line) => response.push(line.trim().to_string()),
+                Err(RecvTimeoutError::Timeout) => return Err("engine timed out".to_string()),
+                Err(RecvTimeoutError::Disconnected) => return Err("engine exited".to_string()),
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str, timeout: Duration) -> Result<Vec<String>, String> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("couldn't send {command}: {e}"))?;
+        let response = self.read_response(timeout)?;
+        match response.first() {
+            Some(line) if line.starts_with("err") | line.starts_with("invalidmove") => {
+                Err(format!("{command} failed: {line}"))
+            }
+            _ => Ok(response),
+        }
+    }
+}
+
+impl Drop for UhpEngine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// HH:MM:SS limit for a search, UHP only deals in whole seconds so the time is rounded down
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// Small generator for the random opening moves, seeded per pair of games
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+fn opening(args: &Args, seed: u64, draw_rules: DrawRules) -> Result<Game, String> {
+    let mut game = Game::load(&args.game_type, draw_rules).map_err(|e| e.to_string())?;
+    let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1);
+    for _ in 0..args.random_plies {
+        let moves = game.compute_valid_moves();
+        if (game.state != GameState::InProgress) | moves.is_empty() {
+            break;
+        }
+        game.try_play_move(moves[rng.next(moves.len())])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(game)
+}
+
+// Plays one game from the opening, returns the winner if any and why the game ended
+fn play_game(
+    engines: &mut [UhpEngine; 2],
+    white: usize,
+    mut game: Game,
+    limit: &Limit,
+) -> (Option<usize>, String) {
+    let opening = game.full_game_string();
+    for engine in engines.iter_mut() {
+        if let Err(e) = engine.send(&format!("newgame {opening}"), COMMAND_TIMEOUT) {
+            return (
+                None,
+                format!("{} couldn't start the game: {e}", engine.name),
+            );
+        }
+    }
+
+    let mut clocks = match limit {
+        Limit::Clock { base, .. } => [*base; 2],
+        Limit::Depth(_) => [f64::INFINITY; 2],
+    };
+    loop {
+        match game.state {
+            GameState::WhiteWins => return (Some(white), "white won".to_string()),
+            GameState::BlackWins => return (Some(1 - white), "black won".to_string()),
+            GameState::Draw => return (None, "draw".to_string()),
+            _ => {}
+        }
+        // Colours alternate with every move, passes included
+        let mover = match game.n_moves() % 2 {
+            0 => white,
+            _ => 1 - white,
+        };
+        let opponent = 1 - mover;
+        let (command, timeout) = match limit {
+            Limit::Clock { increment, .. } => {
+                let budget = clocks[mover] / MOVES_TO_GO as f64 + increment;
+                // Less than a second can't be asked for, the quickest search is played instead
+                let command = if budget >= 1.0 {
+                    format!("bestmove time {}", format_time(budget))
+                } else {
+                    "bestmove depth 1".to_string()
+                };
+                (command, Duration::from_secs_f64(clocks[mover]))
+            }
+            Limit::Depth(depth) => (format!("bestmove depth {depth}"), DEPTH_TIMEOUT),
+        };
+
+        let start = Instant::now();
+        let response = engines[mover].send(&command, timeout);
+        let elapsed = start.elapsed().as_secs_f64();
+        let name = engines[mover].name.clone();
+        let move_str = match response {
+            Ok(response) => response.first().cloned().unwrap_or_default(),
+            Err(e) => return (Some(opponent), format!("{name} failed to move: {e}")),
+        };
+        if let Limit::Clock { increment, .. } = limit {
+            clocks[mover] -= elapsed;
+            if clocks[mover] < 0.0 {
+                return (Some(opponent), format!("{name} lost on time"));
+            }
+            clocks[mover] += increment;
+        }
+
+        // The referee checks the move and forwards it as it wrote it down
+        let played = Move::from_str(&move_str)
+            .map_err(|_| format!("can't read {move_str}"))
+            .and_then(|m| game.try_play_move(m).map_err(|e| e.to_string()));
+        if let Err(e) = played {
+            return (Some(opponent), format!("{name} played {move_str}: {e}"));
+        }
+        let moves_string = game.moves_string();
+        let move_str = moves_string.rsplit(';').next().unwrap_or_default();
+        for (i, engine) in engines.iter_mut().enumerate() {
+            if let Err(e) = engine.send(&format!("play {move_str}"), COMMAND_TIMEOUT) {
+                return (
+                    Some(1 - i),
+                    format!("{} rejected {move_str}: {e}", engine.name),
+                );
+            }
+        }
+    }
+}
+
+fn print_result(result: &MatchResult, sprt: &Option<Sprt>) {
+    println!(
+        "Score of engine1 vs engine2: {} - {} - {} [{:.3}] {}",
+        result.wins,
+        result.losses,
+        result.draws,
+        result.score(),
+        result.games()
+    );
+    let (low, high) = result.elo_interval();
+    let error = (high - low) / 2.0;
+    if error.is_finite() {
+        println!("Elo difference: {:.1} +/- {error:.1}", result.elo());
+    } else {
+        // No error bars until both engines scored and lost points
+        println!(
+            "Elo difference: {:.1}, 95% interval [{low:.1}, {high:.1}]",
+            result.elo()
+        );
+    }
+    if let Some(sprt) = sprt {
+        let (lower, upper) = sprt.bounds();
+        let decision = match result.sprt(sprt) {
+            SprtResult::H0 => "H0 accepted",
+            SprtResult::H1 => "H1 accepted",
+            SprtResult::Continue => "no decision yet",
+        };
+        println!(
+            "SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}) {decision}",
+            sprt.elo0,
+            sprt.elo1,
+            result.llr(sprt),
+            lower,
+            upper
+        );
+    }
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+    let mut engines = [0, 1].map(|i| {
+        UhpEngine::start(&args.engines[i], &args.options[i]).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        })
+    });
+    println!("engine1: {}", engines[0].name);
+    println!("engine2: {}", engines[1].name);
+
+    let draw_rules = DrawRules {
+        repetition: true,
+        max_turns: args.max_turns,
+    };
+    let mut result = MatchResult::default();
+    for i in 0..args.games {
+        // Both engines play each opening once with each colour
+        let game = opening(&args, (i / 2) as u64 + 1, draw_rules).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        });
+        let white = (i % 2) as usize;
+        let (winner, reason) = play_game(&mut engines, white, game, &args.limit);
+        match winner {
+            Some(0) => result.wins += 1,
+            Some(_) => result.losses += 1,
+            None => result.draws += 1,
+        }
+        println!("game {}: engine{} white, {reason}", i + 1, white + 1,);
+        print_result(&result, &args.sprt);
+
+        if let Some(sprt) = &args.sprt {
+            if result.sprt(sprt) != SprtResult::Continue {
+                break;
+            }
+        }
+    }
+}
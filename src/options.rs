@@ -0,0 +1,258 @@
+use crate::error::HiveError;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    Enum(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionKind {
+    Bool,
+    Int { min: i64, max: i64 },
+    Double { min: f64, max: f64 },
+    Enum(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineOption {
+    pub name: &'static str,
+    kind: OptionKind,
+    value: OptionValue,
+    default: OptionValue,
+}
+
+impl EngineOption {
+    fn new(name: &'static str, kind: OptionKind, default: OptionValue) -> Self {
+        EngineOption {
+            name,
+            kind,
+            value: default.clone(),
+            default,
+        }
+    }
+
+    pub fn value(&self) -> &OptionValue {
+        &self.value
+    }
+
+    // Parse a value as sent by a GUI, checking its type and bounds
+    fn parse_value(&self, value: &str) -> Result<OptionValue, HiveError> {
+        let invalid = || HiveError::Parse(format!("invalid value {value} for {}", self.name));
+        match &self.kind {
+            OptionKind::Bool => match value {
+                "True" => Ok(OptionValue::Bool(true)),
+                "False" => Ok(OptionValue::Bool(false)),
+                _ => Err(invalid()),
+            },
+            OptionKind::Int { min, max } => value
+                .parse::<i64>()
+                .ok()
+                .filter(|v| (min..=max).contains(&v))
+                .map(OptionValue::Int)
+                .ok_or_else(invalid),
+            OptionKind::Double { min, max } => value
+                .parse::<f64>()
+                .ok()
+                .filter(|v| (min..=max).contains(&v))
+                .map(OptionValue::Double)
+                .ok_or_else(invalid),
+            OptionKind::Enum(values) => values
+                .iter()
+                .find(|&&v| v == value)
+                .map(|v| OptionValue::Enum(v.to_string()))
+                .ok_or_else(invalid),
+        }
+    }
+}
+
+impl Display for OptionValue {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            OptionValue::Bool(true) => write!(f, "True"),
+            OptionValue::Bool(false) => write!(f, "False"),
+            OptionValue::Int(v) => write!(f, "{v}"),
+            OptionValue::Double(v) => write!(f, "{v}"),
+            OptionValue::Enum(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+impl Display for EngineOption {
+    // Name;Type;Value;Default[;Min;Max] or Name;enum;Value;Default;Values...
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let kind = match self.kind {
+            OptionKind::Bool => "bool",
+            OptionKind::Int { .. } => "int",
+            OptionKind::Double { .. } => "double",
+            OptionKind::Enum(_) => "enum",
+        };
+        write!(f, "{};{kind};{};{}", self.name, self.value, self.default)?;
+        match &self.kind {
+            OptionKind::Bool => Ok(()),
+            OptionKind::Int { min, max } => write!(f, ";{min};{max}"),
+            OptionKind::Double { min, max } => write!(f, ";{min};{max}"),
+            OptionKind::Enum(values) => write!(f, ";{}", values.join(";")),
+        }
+    }
+}
+
+pub const SEARCH_ALGORITHMS: [&str; 4] = ["AlphaBeta", "Negamax", "Iterative", "Parallel"];
+pub const EVALUATIONS: [&str; 2] = ["PieceCount", "QueenPressure"];
+
+// Options exposed to GUIs through the UHP options command
+pub struct Options {
+    options: Vec<EngineOption>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Options {
+    pub fn new() -> Self {
+        let options = vec![
+            EngineOption::new(
+                "MaxDepth",
+                OptionKind::Int { min: 1, max: 20 },
+                OptionValue::Int(3),
+            ),
+            // Seconds, 0 for no limit
+            EngineOption::new(
+                "MaxTime",
+                OptionKind::Double {
+                    min: 0.0,
+                    max: 3600.0,
+                },
+                OptionValue::Double(0.0),
+            ),
+            // Only used by the Parallel search, the other algorithms run on one thread
+            EngineOption::new(
+                "MaxThreads",
+                OptionKind::Int { min: 1, max: 64 },
+                OptionValue::Int(1),
+            ),
+            EngineOption::new(
+                "HashSizeMB",
+                OptionKind::Int { min: 1, max: 4096 },
+                OptionValue::Int(64),
+            ),
+            EngineOption::new(
+                "SearchAlgorithm",
+                OptionKind::Enum(&SEARCH_ALGORITHMS),
+                OptionValue::Enum("AlphaBeta".to_string()),
+            ),
+            EngineOption::new(
+                "Evaluation",
+                OptionKind::Enum(&EVALUATIONS),
+                OptionValue::Enum("PieceCount".to_string()),
+            ),
+            EngineOption::new("RepetitionDraw", OptionKind::Bool, OptionValue::Bool(false)),
+            // Full turns, 0 for no limit
+            EngineOption::new(
+                "MaxTurns",
+                OptionKind::Int { min: 0, max: 1000 },
+                OptionValue::Int(0),
+            ),
+        ];
+        Options { options }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EngineOption> {
+        self.options.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Result<&EngineOption, HiveError> {
+        self.options
+            .iter()
+            .find(|o| o.name == name)
+            .ok_or(HiveError::InvalidCommand(format!("unknown option {name}")))
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<&EngineOption, HiveError> {
+        let option = self
+            .options
+            .iter_mut()
+            .find(|o| o.name == name)
+            .ok_or(HiveError::InvalidCommand(format!("unknown option {name}")))?;
+        option.value = option.parse_value(value)?;
+        Ok(option)
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        match self.get(name).map(|o| o.value()) {
+            Ok(OptionValue::Bool(v)) => *v,
+            _ => panic!("{name} is not a bool option."),
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> i64 {
+        match self.get(name).map(|o| o.value()) {
+            Ok(OptionValue::Int(v)) => *v,
+            _ => panic!("{name} is not an int option."),
+        }
+    }
+
+    pub fn get_double(&self, name: &str) -> f64 {
+        match self.get(name).map(|o| o.value()) {
+            Ok(OptionValue::Double(v)) => *v,
+            _ => panic!("{name} is not a double option."),
+        }
+    }
+
+    pub fn get_enum(&self, name: &str) -> &str {
+        match self.get(name).map(|o| o.value()) {
+            Ok(OptionValue::Enum(v)) => v,
+            _ => panic!("{name} is not an enum option."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::Options;
+
+    #[test]
+    fn option_strings() {
+        let options = Options::new();
+        assert_eq!(
+            options.get("MaxDepth").unwrap().to_string(),
+            "MaxDepth;int;3;3;1;20"
+        );
+        assert_eq!(
+            options.get("RepetitionDraw").unwrap().to_string(),
+            "RepetitionDraw;bool;False;False"
+        );
+        assert_eq!(
+            options.get("Evaluation").unwrap().to_string(),
+            "Evaluation;enum;PieceCount;PieceCount;PieceCount;QueenPressure"
+        );
+        assert!(options.get("DummyOption").is_err());
+    }
+
+    #[test]
+    fn set_options() {
+        let mut options = Options::new();
+        assert_eq!(
+            options.set("MaxTime", "2.5").unwrap().to_string(),
+            "MaxTime;double;2.5;0;0;3600"
+        );
+        assert_eq!(options.get_double("MaxTime"), 2.5);
+        options.set("RepetitionDraw", "True").unwrap();
+        assert!(options.get_bool("RepetitionDraw"));
+        options.set("SearchAlgorithm", "Iterative").unwrap();
+        assert_eq!(options.get_enum("SearchAlgorithm"), "Iterative");
+
+        // Out of bounds or wrongly typed values leave the option untouched
+        assert!(options.set("MaxDepth", "0").is_err());
+        assert!(options.set("MaxDepth", "deep").is_err());
+        assert!(options.set("RepetitionDraw", "true").is_err());
+        assert!(options.set("SearchAlgorithm", "Random").is_err());
+        assert_eq!(options.get_int("MaxDepth"), 3);
+    }
+}
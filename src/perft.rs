@@ -1,39 +1,75 @@
-mod engine;
-mod logic;
-
-use crate::engine::Engine;
-use crate::logic::game::{Game, GameState};
+use hive_rust::logic::game::Game;
+use hive_rust::logic::perft::{divide, perft};
+use std::env;
+use std::process;
 use std::time::Instant;
 
-fn count_moves_depth(g: Game, depth: u32) -> usize {
-    if depth == 0 {
-        let n_valid_moves = g.compute_valid_moves().len();
-        if n_valid_moves == 0 {
-            println!("Should pass")
-        }
-        if g.state != GameState::InProgress {
-            println!("Should pass")
-        }
-        return n_valid_moves;
-    } else {
-        let mut total_moves = 0;
-        for m in g.compute_valid_moves() {
-            let mut new_e = g.clone();
-            new_e.play_move(m);
-            total_moves += count_moves_depth(new_e, depth - 1);
+const USAGE: &str = "usage: perft [DEPTH] [--game GameString] [--divide] [--threads N]";
+
+struct Args {
+    depth: u32,
+    game_string: String,
+    divide: bool,
+    threads: usize,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        depth: 6,
+        game_string: "Base".to_string(),
+        divide: false,
+        threads: 1,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--game" => args.game_string = iter.next().ok_or("missing GameString")?,
+            "--divide" => args.divide = true,
+            "--threads" => {
+                let threads = iter.next().ok_or("missing number of threads")?;
+                args.threads = threads
+                    .parse()
+                    .ok()
+                    .filter(|&t| t > 0)
+                    .ok_or(format!("invalid number of threads {threads}"))?;
+            }
+            depth => {
+                args.depth = depth
+                    .parse()
+                    .map_err(|_| format!("invalid depth {depth}"))?
+            }
         }
-        total_moves
     }
+    Ok(args)
 }
 
 fn main() {
-    let mut e = Engine::new();
-    e.new_game();
-    let g = e.game.unwrap();
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+    let mut game = Game::from_game_string(&args.game_string).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(1);
+    });
+
     let now = Instant::now();
-    let moves_depth = count_moves_depth(g, 6);
+    let moves_depth = if args.divide | (args.threads > 1) {
+        let divided = divide(&game, args.depth, args.threads);
+        if args.divide {
+            for (m, nodes) in &divided {
+                println!("{m}: {nodes}");
+            }
+        }
+        divided.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft(&mut game, args.depth)
+    };
     let elapsed = now.elapsed();
     let kn_per_s = (moves_depth as f32) / (1000.0 * elapsed.as_secs_f32());
-    println!("{moves_depth} nodes computed in {elapsed:?}.");
+    println!(
+        "perft {}: {moves_depth} nodes computed in {elapsed:?}.",
+        args.depth
+    );
     println!("{kn_per_s} KN/s");
 }
@@ -0,0 +1,161 @@
+// Results of a match, counted for the first engine
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct MatchResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+// Sequential probability ratio test between two Elo differences
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // Probability of accepting H1 when H0 is true
+    pub alpha: f64,
+    // Probability of accepting H0 when H1 is true
+    pub beta: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SprtResult {
+    // The first engine is no more than elo0 stronger
+    H0,
+    // The first engine is at least elo1 stronger
+    H1,
+    Continue,
+}
+
+// Expected score against an opponent elo points weaker
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+impl MatchResult {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Average points per game, a draw being half a point
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    // Variance of the points of a single game
+    fn variance(&self) -> f64 {
+        let n = self.games() as f64;
+        let score = self.score();
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / n
+    }
+
+    // Elo difference between the two engines, infinite after a clean sweep
+    pub fn elo(&self) -> f64 {
+        score_to_elo(self.score())
+    }
+
+    // Elo differences bounding the 95% confidence interval
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let margin = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let score = self.score();
+        (
+            score_to_elo((score - margin).max(0.0)),
+            score_to_elo((score + margin).min(1.0)),
+        )
+    }
+
+    // Log likelihood ratio of H1 against H0, using the normal approximation
+    pub fn llr(&self, sprt: &Sprt) -> f64 {
+        let variance = self.variance();
+        if (self.games() == 0) | (variance == 0.0) {
+            return 0.0;
+        }
+        let s0 = elo_to_score(sprt.elo0);
+        let s1 = elo_to_score(sprt.elo1);
+        self.games() as f64 * (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn sprt(&self, sprt: &Sprt) -> SprtResult {
+        let llr = self.llr(sprt);
+        let (lower, upper) = sprt.bounds();
+        if llr >= upper {
+            SprtResult::H1
+        } else if llr <= lower {
+            SprtResult::H0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+impl Sprt {
+    // Log likelihood ratios at which H0 or H1 is accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::{MatchResult, Sprt, SprtResult};
+
+    fn result(wins: u32, draws: u32, losses: u32) -> MatchResult {
+        MatchResult {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn elo_difference() {
+        let even = result(10, 20, 10);
+        assert_eq!(even.score(), 0.5);
+        assert!(even.elo().abs() < 1e-9);
+        let (low, high) = even.elo_interval();
+        assert!((low + high).abs() < 1e-9);
+        assert!((high - 77.4).abs() < 0.1, "{high}");
+
+        // Scoring 75% is about 191 elo
+        assert!((result(3, 0, 1).elo() - 190.85).abs() < 0.01);
+        let stronger = result(30, 10, 10);
+        let (low, high) = stronger.elo_interval();
+        assert!((low < stronger.elo()) & (stronger.elo() < high));
+        assert_eq!(result(5, 0, 0).elo(), f64::INFINITY);
+    }
+
+    #[test]
+    fn sprt_decisions() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+
+        assert_eq!(result(0, 0, 0).sprt(&sprt), SprtResult::Continue);
+        assert_eq!(result(6, 8, 4).sprt(&sprt), SprtResult::Continue);
+        assert_eq!(result(600, 200, 400).sprt(&sprt), SprtResult::H1);
+        assert_eq!(result(400, 200, 600).sprt(&sprt), SprtResult::H0);
+        // Equal engines end up accepting H0
+        assert_eq!(result(3000, 4000, 3000).sprt(&sprt), SprtResult::H0);
+    }
+}
@@ -1,51 +1,157 @@
-mod engine;
-mod logic;
-
-use crate::engine::Engine;
-use std::io::{BufRead, BufReader, Write};
-use std::net::{TcpListener, TcpStream};
-use std::{thread, time};
-
-fn handle_client(mut stream: &mut TcpStream, engine: &mut Engine) {
-    let mut response = String::new();
-    let mut conn = BufReader::new(&mut stream);
-    conn.read_line(&mut response).expect("unable to read");
-
-    let server_response = &response[4..];
-    println!("Request : {:?}", server_response.to_string());
-    let engine_response = engine.process_command(server_response.to_string());
-    let server_response = match engine_response {
-        Ok(r) => {
-            println!("{}", format!("Sending back {r}"));
-            format!("{r}\nok")
+use hive_rust::engine::Engine;
+use log::debug;
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process;
+use std::thread;
+
+const USAGE: &str = "usage: server [--address HOST:PORT] [--framing line|length]";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8181";
+// Largest request accepted in length framing
+const MAX_REQUEST_LEN: usize = 1 << 20;
+
+// How requests and replies are delimited on the stream
+#[derive(Copy, Clone, PartialEq)]
+enum Framing {
+    // One UHP command per line, replies are the response lines followed by ok
+    Line,
+    // Requests and replies are prefixed by their length in bytes, as a 4 bytes big endian integer
+    Length,
+}
+
+struct Args {
+    address: String,
+    framing: Framing,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        address: DEFAULT_ADDRESS.to_string(),
+        framing: Framing::Line,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--address" => args.address = iter.next().ok_or("missing address")?,
+            "--framing" => {
+                args.framing = match iter.next().as_deref() {
+                    Some("line") => Framing::Line,
+                    Some("length") => Framing::Length,
+                    Some(framing) => return Err(format!("invalid framing {framing}")),
+                    None => return Err("missing framing".to_string()),
+                }
+            }
+            arg => return Err(format!("unknown argument {arg}")),
         }
+    }
+    Ok(args)
+}
+
+// Next command sent by the client, None once it disconnected
+fn read_request(reader: &mut impl BufRead, framing: Framing) -> io::Result<Option<String>> {
+    match framing {
+        Framing::Line => {
+            let mut request = String::new();
+            match reader.read_line(&mut request)? {
+                0 => Ok(None),
+                _ => Ok(Some(request)),
+            }
+        }
+        Framing::Length => {
+            let mut len = [0; 4];
+            match reader.read_exact(&mut len) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                result => result?,
+            }
+            let len = u32::from_be_bytes(len) as usize;
+            if len > MAX_REQUEST_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("request of {len} bytes is too long"),
+                ));
+            }
+            let mut request = vec![0; len];
+            reader.read_exact(&mut request)?;
+            String::from_utf8(request)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+    }
+}
+
+fn write_response(writer: &mut impl Write, framing: Framing, response: &str) -> io::Result<()> {
+    if framing == Framing::Length {
+        writer.write_all(&(response.len() as u32).to_be_bytes())?;
+    }
+    writer.write_all(response.as_bytes())?;
+    writer.flush()
+}
+
+// Every connection plays its own games with its own engine
+fn handle_client(stream: TcpStream, peer: SocketAddr, framing: Framing) {
+    let mut engine = Engine::new();
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
         Err(e) => {
-            println!("{}", format!("Sending back err {e}"));
-            format!("err {e}\nok")
+            eprintln!("{peer}: failed to clone stream: {e}");
+            return;
         }
     };
+    let mut reader = BufReader::new(stream);
+
+    loop {
+        let request = match read_request(&mut reader, framing) {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("{peer}: failed to read request: {e}");
+                break;
+            }
+        };
+
+        debug!("{peer}: request {request:?}");
+        let response = engine.uhp_response(request);
+        debug!("{peer}: sending back {response:?}");
 
-    stream
-        .write(server_response.as_bytes())
-        .expect("Failed to write response to client");
+        if let Err(e) = write_response(&mut writer, framing, &response) {
+            eprintln!("{peer}: failed to write response: {e}");
+            break;
+        }
+    }
+    println!("{peer}: disconnected");
 }
 
 fn main() {
-    let mut engine = Engine::new();
-
-    let listener = TcpListener::bind("127.0.0.1:8181").expect("Failed to bind to address");
-    println!("Server listening on 127.0.0.1:8181");
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+    let listener = TcpListener::bind(&args.address).unwrap_or_else(|e| {
+        eprintln!("failed to bind to {}: {e}", args.address);
+        process::exit(1);
+    });
+    // Port 0 binds to any free port, the actual one is printed
+    match listener.local_addr() {
+        Ok(address) => println!("Server listening on {address}"),
+        Err(_) => println!("Server listening on {}", args.address),
+    }
 
     for stream in listener.incoming() {
-        match stream {
-            Ok(mut stream) => loop {
-                let sleep_duration = time::Duration::from_millis(200);
-                thread::sleep(sleep_duration);
-                handle_client(&mut stream, &mut engine);
-            },
+        let stream = match stream {
+            Ok(stream) => stream,
             Err(e) => {
-                eprintln!("Failed to establish connection: {}", e);
+                eprintln!("Failed to establish connection: {e}");
+                continue;
+            }
+        };
+        match stream.peer_addr() {
+            Ok(peer) => {
+                println!("{peer}: connected");
+                let framing = args.framing;
+                thread::spawn(move || handle_client(stream, peer, framing));
             }
+            Err(e) => eprintln!("Failed to get peer address: {e}"),
         }
     }
 }
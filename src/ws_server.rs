@@ -0,0 +1,97 @@
+use hive_rust::engine::Engine;
+use log::debug;
+use std::env;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::process;
+use std::thread;
+use tungstenite::error::ProtocolError;
+use tungstenite::{accept, Error, Message};
+
+const USAGE: &str = "usage: server-ws [--address HOST:PORT]";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8182";
+
+fn parse_args() -> Result<String, String> {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--address" => address = iter.next().ok_or("missing address")?,
+            arg => return Err(format!("unknown argument {arg}")),
+        }
+    }
+    Ok(address)
+}
+
+// One UHP command per text message, answered by a message holding the full response
+fn handle_client(stream: TcpStream, peer: SocketAddr) {
+    let mut socket = match accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("{peer}: handshake failed: {e}");
+            return;
+        }
+    };
+    let mut engine = Engine::new();
+
+    loop {
+        let request = match socket.read() {
+            Ok(Message::Text(request)) => request,
+            Ok(Message::Binary(_)) => {
+                eprintln!("{peer}: binary messages are not supported");
+                continue;
+            }
+            // Pings are answered by the library
+            Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)) => continue,
+            Ok(Message::Close(_))
+            | Err(Error::ConnectionClosed)
+            | Err(Error::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => break,
+            Err(e) => {
+                eprintln!("{peer}: failed to read request: {e}");
+                break;
+            }
+        };
+
+        debug!("{peer}: request {request:?}");
+        let response = engine.uhp_response(request);
+        debug!("{peer}: sending back {response:?}");
+
+        if let Err(e) = socket.send(Message::Text(response)) {
+            eprintln!("{peer}: failed to write response: {e}");
+            break;
+        }
+    }
+    println!("{peer}: disconnected");
+}
+
+fn main() {
+    let address = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        process::exit(1);
+    });
+    let listener = TcpListener::bind(&address).unwrap_or_else(|e| {
+        eprintln!("failed to bind to {address}: {e}");
+        process::exit(1);
+    });
+    // Port 0 binds to any free port, the actual one is printed
+    match listener.local_addr() {
+        Ok(address) => println!("Server listening on {address}"),
+        Err(_) => println!("Server listening on {address}"),
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to establish connection: {e}");
+                continue;
+            }
+        };
+        match stream.peer_addr() {
+            Ok(peer) => {
+                println!("{peer}: connected");
+                thread::spawn(move || handle_client(stream, peer));
+            }
+            Err(e) => eprintln!("Failed to get peer address: {e}"),
+        }
+    }
+}
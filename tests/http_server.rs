@@ -0,0 +1,122 @@
+mod common;
+
+use common::Server;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+fn start_server() -> Server {
+    Server::start(env!("CARGO_BIN_EXE_server-http"), &[])
+}
+
+// Status code and JSON body of the reply
+fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(&server.address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        server.address,
+        body.len()
+    )
+    .unwrap();
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).unwrap();
+    let (head, body) = reply.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+fn post(server: &Server, path: &str, body: Value) -> (u16, Value) {
+    request(server, "POST", path, &body.to_string())
+}
+
+#[test]
+fn valid_moves_and_play() {
+    let server = start_server();
+    let (status, reply) = post(&server, "/validmoves", json!({ "game": "Base+M" }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["game"], "Base+M;InProgress;White[1]");
+    assert_eq!(reply["moves"].as_array().unwrap().len(), 5);
+
+    let (status, reply) = post(
+        &server,
+        "/play",
+        json!({ "game": "Base;InProgress;White[2];wS1;bG1 -wS1", "move": "wQ wS1/" }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(
+        reply["game"],
+        "Base;InProgress;Black[2];wS1;bG1 -wS1;wQ wS1/"
+    );
+    assert_eq!(reply["state"], "InProgress");
+
+    let (status, reply) = post(
+        &server,
+        "/play",
+        json!({ "game": "Base;InProgress;White[2];wS1;bG1 -wS1", "move": "wQ -bG1" }),
+    );
+    assert_eq!(status, 400);
+    assert_eq!(reply["error"], "invalid move wQ -bG1");
+}
+
+#[test]
+fn best_move_with_principal_variation() {
+    let server = start_server();
+    // Black surrounds the white queen with bA2 -wA3
+    let game = "Base;InProgress;Black[8];wS1;bA1 /wS1;wS2 \\wS1;bQ -bA1;wQ wS2/;bS1 bQ\\;\
+                wA1 wS2-;bG1 -bQ;wA2 \\wQ;bB1 -bG1;wB1 -wQ;bG2 bS1\\;wA2 wQ-;bA2 bB1\\;wA3 wQ/";
+    let (status, reply) = post(
+        &server,
+        "/bestmove",
+        json!({ "game": game, "depth": 2, "evaluation": "QueenPressure" }),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(reply["move"], "bA2 -wA3");
+    let won = game.replacen("InProgress;Black[8]", "BlackWins;White[9]", 1);
+    assert_eq!(reply["game"], format!("{won};bA2 -wA3"));
+    assert!(reply["evaluation"].as_i64().unwrap() > 0);
+    assert_eq!(reply["pv"], json!(["bA2 -wA3"]));
+
+    let (status, reply) = post(&server, "/bestmove", json!({ "game": "Base", "time": 0.2 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["pv"][0], reply["move"]);
+}
+
+#[test]
+fn best_move_stops_at_depth() {
+    let server = start_server();
+    // Middlegame position with no forced win
+    let game = "Base;InProgress;White[10];wS1;bB1 \\wS1;wQ /wS1;bB2 bB1/;wB1 /wQ;bQ \\bB2;\
+                wA1 /wB1;bA1 bQ-;wS2 /wA1;bA1 -wS2;wA2 wQ-;bA1 bA1\\;wA2 \\bQ;bA1 wQ-;\
+                wA2 wS2-;bA2 bQ-;wA2 wA1-;bG1 -bB1";
+    let now = Instant::now();
+    let (status, reply) = post(&server, "/bestmove", json!({ "game": game, "depth": 1 }));
+    assert_eq!(status, 200);
+    assert_eq!(reply["depth"], 1);
+    assert_eq!(reply["pv"].as_array().unwrap().len(), 1);
+    assert!(now.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn bad_requests() {
+    let server = start_server();
+    let (status, reply) = post(&server, "/validmoves", json!({ "game": "Chess" }));
+    assert_eq!(status, 400);
+    assert!(reply["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid game type Chess"));
+    let (status, _) = post(&server, "/bestmove", json!({ "game": "Base", "depth": 0 }));
+    assert_eq!(status, 400);
+    let (status, _) = request(&server, "POST", "/play", "not json");
+    assert_eq!(status, 400);
+    let (status, _) = request(&server, "GET", "/validmoves", "");
+    assert_eq!(status, 405);
+    let (status, _) = post(&server, "/resign", json!({}));
+    assert_eq!(status, 404);
+}
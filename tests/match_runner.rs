@@ -0,0 +1,74 @@
+use std::process::Command;
+
+fn run_match(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_match"))
+        .args(args)
+        .output()
+        .expect("Couldn't run match.");
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn engines_play_both_colours() {
+    let engine = env!("CARGO_BIN_EXE_hive-rust");
+    let (success, stdout) = run_match(&[
+        "--engine1",
+        engine,
+        "--engine2",
+        engine,
+        "--option2",
+        "Evaluation=QueenPressure",
+        "--depth",
+        "1",
+        "--games",
+        "2",
+        "--max-turns",
+        "8",
+        "--sprt",
+        "0,10",
+    ]);
+    assert!(success, "{stdout}");
+    assert!(stdout.contains("game 1: engine1 white"), "{stdout}");
+    assert!(stdout.contains("game 2: engine2 white"), "{stdout}");
+    let score = stdout
+        .lines()
+        .rfind(|line| line.starts_with("Score of engine1 vs engine2"))
+        .unwrap();
+    assert!(score.ends_with(" 2"), "{score}");
+    assert!(stdout.contains("SPRT [0, 10]"), "{stdout}");
+}
+
+#[test]
+fn clock_limited_games() {
+    let engine = env!("CARGO_BIN_EXE_hive-rust");
+    let (success, stdout) = run_match(&[
+        "--engine1",
+        engine,
+        "--engine2",
+        engine,
+        "--tc",
+        "20+1",
+        "--games",
+        "2",
+        "--max-turns",
+        "4",
+    ]);
+    assert!(success, "{stdout}");
+    // Nobody runs out of time with a one second search per move
+    assert!(!stdout.contains("lost on time"), "{stdout}");
+    assert!(!stdout.contains("failed to move"), "{stdout}");
+    assert_eq!(stdout.matches("draw").count(), 2, "{stdout}");
+}
+
+#[test]
+fn invalid_arguments() {
+    let engine = env!("CARGO_BIN_EXE_hive-rust");
+    assert!(!run_match(&["--engine1", engine]).0);
+    assert!(!run_match(&["--engine1", engine, "--engine2", engine, "--tc", "fast"]).0);
+    // Each move would get less than the second UHP can ask for
+    assert!(!run_match(&["--engine1", engine, "--engine2", engine, "--tc", "10+0.1"]).0);
+    assert!(!run_match(&["--engine1", engine, "--engine2", "./no-such-engine"]).0);
+}
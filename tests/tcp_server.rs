@@ -0,0 +1,137 @@
+mod common;
+
+use common::Server;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
+
+fn start_server(framing: &str) -> Server {
+    Server::start(env!("CARGO_BIN_EXE_server"), &["--framing", framing])
+}
+
+fn connect(server: &Server) -> TcpStream {
+    let stream = TcpStream::connect(&server.address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    stream
+}
+
+// Lines of the next response, up to and including ok
+fn read_response(reader: &mut impl BufRead) -> Vec<String> {
+    let mut lines = vec![];
+    loop {
+        let mut line = String::new();
+        assert!(reader.read_line(&mut line).unwrap() > 0, "{lines:?}");
+        assert!(line.ends_with('\n'));
+        let line = line.trim_end().to_string();
+        lines.push(line.clone());
+        if line == "ok" {
+            return lines;
+        }
+    }
+}
+
+#[test]
+fn line_framing() {
+    let server = start_server("line");
+    let mut stream = connect(&server);
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    stream.write_all(b"info\n").unwrap();
+    assert_eq!(read_response(&mut reader), ["0.1", "ok"]);
+
+    // Several commands in one packet are answered in order
+    stream
+        .write_all(b"newgame Base\nplay wS1\r\nplay wQ wS1-\n")
+        .unwrap();
+    assert_eq!(
+        read_response(&mut reader),
+        ["Base;InProgress;White[1]", "ok"]
+    );
+    assert_eq!(
+        read_response(&mut reader),
+        ["Base;InProgress;Black[1];wS1", "ok"]
+    );
+    assert_eq!(
+        read_response(&mut reader),
+        ["invalidmove invalid move wQ wS1-", "ok"]
+    );
+
+    // A command split across packets is only answered once complete
+    stream.write_all(b"undo").unwrap();
+    stream.flush().unwrap();
+    thread::sleep(Duration::from_millis(50));
+    stream.write_all(b"\n").unwrap();
+    assert_eq!(
+        read_response(&mut reader),
+        ["Base;InProgress;White[1]", "ok"]
+    );
+
+    stream.write_all(b"dance\n").unwrap();
+    assert_eq!(
+        read_response(&mut reader),
+        ["err unknown command dance", "ok"]
+    );
+}
+
+#[test]
+fn length_framing() {
+    let server = start_server("length");
+    let mut stream = connect(&server);
+    let mut request = |command: &str| {
+        let mut packet = (command.len() as u32).to_be_bytes().to_vec();
+        packet.extend_from_slice(command.as_bytes());
+        stream.write_all(&packet).unwrap();
+        let mut len = [0; 4];
+        stream.read_exact(&mut len).unwrap();
+        let mut response = vec![0; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut response).unwrap();
+        String::from_utf8(response).unwrap()
+    };
+
+    assert_eq!(request("info"), "0.1\nok\n");
+    assert_eq!(
+        request("newgame Base+M"),
+        "Base+M;InProgress;White[1]\nok\n"
+    );
+    assert_eq!(request("play wM"), "Base+M;InProgress;Black[1];wM\nok\n");
+    assert_eq!(request("pass"), "invalidmove invalid move pass\nok\n");
+}
+
+#[test]
+fn clients_have_their_own_games() {
+    let server = start_server("line");
+    let mut first = connect(&server);
+    let mut second = connect(&server);
+    let mut first_reader = BufReader::new(first.try_clone().unwrap());
+    let mut second_reader = BufReader::new(second.try_clone().unwrap());
+
+    first.write_all(b"newgame Base\n").unwrap();
+    assert_eq!(
+        read_response(&mut first_reader),
+        ["Base;InProgress;White[1]", "ok"]
+    );
+    second.write_all(b"validmoves\n").unwrap();
+    assert_eq!(
+        read_response(&mut second_reader),
+        ["err no game in progress", "ok"]
+    );
+
+    // A client leaving doesn't bring the server down
+    drop(second_reader);
+    drop(second);
+    first.write_all(b"play wA1\n").unwrap();
+    assert_eq!(
+        read_response(&mut first_reader),
+        ["Base;InProgress;Black[1];wA1", "ok"]
+    );
+    let mut third = connect(&server);
+    let mut third_reader = BufReader::new(third.try_clone().unwrap());
+    third.write_all(b"newgame\n").unwrap();
+    assert_eq!(
+        read_response(&mut third_reader),
+        ["Base;InProgress;White[1]", "ok"]
+    );
+}
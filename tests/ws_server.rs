@@ -0,0 +1,71 @@
+mod common;
+
+use common::Server;
+use std::net::TcpStream;
+use std::time::Duration;
+use tungstenite::{client, Message, WebSocket};
+
+fn connect(server: &Server) -> WebSocket<TcpStream> {
+    let stream = TcpStream::connect(&server.address).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    let (socket, _) = client(format!("ws://{}", server.address), stream).unwrap();
+    socket
+}
+
+fn request(socket: &mut WebSocket<TcpStream>, command: &str) -> String {
+    socket.send(Message::Text(command.to_string())).unwrap();
+    loop {
+        match socket.read().unwrap() {
+            Message::Text(response) => return response,
+            Message::Ping(_) | Message::Pong(_) => continue,
+            message => panic!("unexpected message {message:?}"),
+        }
+    }
+}
+
+#[test]
+fn one_response_per_message() {
+    let server = Server::start(env!("CARGO_BIN_EXE_server-ws"), &[]);
+    let mut socket = connect(&server);
+
+    assert_eq!(request(&mut socket, "info"), "0.1\nok\n");
+    assert_eq!(
+        request(&mut socket, "newgame Base"),
+        "Base;InProgress;White[1]\nok\n"
+    );
+    assert_eq!(
+        request(&mut socket, "play wA1\n"),
+        "Base;InProgress;Black[1];wA1\nok\n"
+    );
+    let valid_moves = request(&mut socket, "validmoves");
+    assert_eq!(valid_moves.lines().count(), 2);
+    assert_eq!(valid_moves.lines().next().unwrap().split(';').count(), 24);
+    assert_eq!(
+        request(&mut socket, "play bQ wA1"),
+        "invalidmove invalid move bQ wA1\nok\n"
+    );
+    assert_eq!(
+        request(&mut socket, "dance"),
+        "err unknown command dance\nok\n"
+    );
+}
+
+#[test]
+fn sockets_have_their_own_games() {
+    let server = Server::start(env!("CARGO_BIN_EXE_server-ws"), &[]);
+    let mut first = connect(&server);
+    let mut second = connect(&server);
+
+    request(&mut first, "newgame Base+P");
+    assert_eq!(
+        request(&mut second, "undo"),
+        "err no game in progress\nok\n"
+    );
+    second.close(None).unwrap();
+    assert_eq!(
+        request(&mut first, "play wP"),
+        "Base+P;InProgress;Black[1];wP\nok\n"
+    );
+}